@@ -1,40 +1,479 @@
 use crate::PATH_TO_STRING_MSG;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use image::{ImageFormat, Rgba};
 use indexmap::IndexMap;
 use log::{debug, trace};
 use serde::de::{MapAccess, Visitor};
 use serde::{de, Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use std::env;
 use std::fmt::Formatter;
 use std::fs::OpenOptions;
 use std::hash::{Hash, Hasher};
 use std::io;
 use std::io::ErrorKind;
 use std::num::ParseIntError;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 const SUPPORTED_AUDIO_EXTENSIONS: [&str; 5] = ["aac", "flac", "m4a", "mp3", "wav"];
+const SVG_EXTENSION: &str = "svg";
 const DEFAULT_WEIGHT: f64 = 1.0;
+// The configuration fields used to extend and include other configuration files
+const EXTENDS: &str = "extends";
+const INCLUDE: &str = "include";
+const ATTRIBUTES: &str = "attributes";
+const NAME: &str = "name";
+const OPTIONS: &str = "options";
+// The optional fields which may be set from the command line even when absent from the
+// configuration, by the path of their parent (where `*` matches any name or index)
+const OPTIONAL_FIELDS: [(&str, &[&str]); 21] = [
+    (
+        "",
+        &[
+            "external_url",
+            "background_color",
+            "collection",
+            "metadata",
+            "hidden",
+            "editions",
+            "edition_output",
+            "media_format",
+            "standard",
+            "metaplex",
+            "erc1155",
+            "tzip21",
+            "cip25",
+            "video",
+        ],
+    ),
+    (
+        "collection",
+        &[
+            "name",
+            "description",
+            "image",
+            "banner",
+            "external_link",
+            "seller_fee_basis_points",
+            "fee_recipient",
+        ],
+    ),
+    ("metadata", &["dna", "fields", "rename", "order"]),
+    ("metadata.fields", &["*"]),
+    ("metadata.rename", &["*"]),
+    ("hidden", &["name", "description", "image"]),
+    ("editions.*", &["start_token", "token_name", "weights"]),
+    ("editions.*.weights", &["*"]),
+    ("editions.*.weights.*", &["*"]),
+    (
+        "metaplex",
+        &[
+            "symbol",
+            "seller_fee_basis_points",
+            "creators",
+            "collection",
+        ],
+    ),
+    ("metaplex.collection", &["name", "family"]),
+    ("erc1155", &["decimals", "properties"]),
+    ("erc1155.properties", &["*"]),
+    ("tzip21", &["symbol", "creators", "rights", "tags"]),
+    ("cip25", &["policy_id", "asset_name", "version"]),
+    (
+        "video",
+        &[
+            "container",
+            "video_codec",
+            "audio_codec",
+            "bitrate",
+            "crf",
+            "preset",
+            "scale",
+            "audio_bitrate",
+            "pixel_format",
+            "framerate",
+            "colorspace",
+            "args",
+        ],
+    ),
+    ("video.args", &["*"]),
+    (
+        "attributes.*",
+        &[
+            "trait_type",
+            "z",
+            "metadata",
+            "duration",
+            "display_type",
+            "max_value",
+        ],
+    ),
+    ("attributes.*.options.*", &["weight", "value", "when"]),
+    ("attributes.*.options.*.when", &["*"]),
+    ("attributes.*.options.*.parts.*", &["z"]),
+];
 
-pub(crate) fn load(source: &PathBuf, config: &str) -> Result<Config> {
-    let config = source.join(config);
-    let config_path = &config.to_str().expect(PATH_TO_STRING_MSG);
+pub(crate) fn load(source: &Path, config: &str, overrides: &[Override]) -> Result<Config> {
+    let config_path = &source.join(config);
+    let config_path = config_path.to_str().expect(PATH_TO_STRING_MSG);
     debug!("loading configuration from '{config_path}'");
-    let file = OpenOptions::new()
-        .read(true)
-        .open(&config)
-        .with_context(|| format!("failed to load configuration from {config_path}"))?;
-    let mut config: Config = serde_json::from_reader(file)
+    let mut config = read(source, Path::new(config), &mut Vec::new())?;
+    for setting in overrides {
+        setting.apply(&mut config)?;
+    }
+    interpolate(&mut config)?;
+    let json = config.clone();
+    let mut config: Config = serde_json::from_value(config)
         .with_context(|| format!("failed to deserialize configuration file from {config_path}"))?;
+    config.json = json;
+
+    // Reverse the attributes (layers), unless ordered explicitly by z-index
+    if config.attributes.iter().all(|a| a.z.is_none()) {
+        config.attributes.reverse();
+    }
 
-    // Reverse the attributes (layers)
-    config.attributes.reverse();
+    // Editions without a start token follow on from the previous edition
+    let mut next_token = config.start_token;
+    for edition in &mut config.editions {
+        let start_token = *edition.start_token.get_or_insert(next_token);
+        next_token = start_token + edition.supply;
+    }
 
     // Validate all configured paths exist and return config if successful
-    config.validate(&source)?;
+    config.validate(source)?;
     Ok(config)
 }
 
+/// Reads a configuration file (relative to the source directory) as JSON, merging it over any
+/// configuration files it extends and importing any attributes it includes.
+fn read(source: &Path, file: &Path, loading: &mut Vec<PathBuf>) -> Result<Value> {
+    let canonical = canonical(source, file)?;
+    if loading.contains(&canonical) {
+        let source = source.canonicalize().unwrap_or_default();
+        return Err(anyhow!(
+            "configuration files have a circular reference: {} -> {}",
+            loading
+                .iter()
+                .map(|f| f
+                    .strip_prefix(&source)
+                    .unwrap_or(f)
+                    .to_str()
+                    .expect(PATH_TO_STRING_MSG))
+                .collect::<Vec<&str>>()
+                .join(" -> "),
+            file.to_str().expect(PATH_TO_STRING_MSG)
+        ));
+    }
+    loading.push(canonical);
+
+    let directory = file.parent().unwrap_or_else(|| Path::new(""));
+    let mut config = read_json(source, file)?;
+    rebase_config(&mut config, directory);
+    if let Some(Value::Array(attributes)) = config.get_mut(ATTRIBUTES) {
+        *attributes = include_attributes(source, directory, std::mem::take(attributes), loading)?;
+    }
+
+    // Merge the config over any configs it extends, in the order specified
+    let extends = match config.as_object_mut().and_then(|c| c.shift_remove(EXTENDS)) {
+        None => vec![],
+        Some(Value::String(file)) => vec![file],
+        Some(value) => serde_json::from_value(value).with_context(|| {
+            format!(
+                "'{EXTENDS}' of {} must be a file name or an array of file names",
+                file.to_str().expect(PATH_TO_STRING_MSG)
+            )
+        })?,
+    };
+    let mut merged = Value::Object(Map::new());
+    for base in extends {
+        merge(&mut merged, read(source, &directory.join(base), loading)?);
+    }
+    merge(&mut merged, config);
+
+    loading.pop();
+    Ok(merged)
+}
+
+/// The canonical path of a configuration file (relative to the source directory), so that a file
+/// referenced by different relative paths (e.g. `sub/../a.json`) is recognised as the same file.
+fn canonical(source: &Path, file: &Path) -> Result<PathBuf> {
+    let path = source.join(file);
+    path.canonicalize().with_context(|| {
+        format!(
+            "failed to load configuration from {}",
+            path.to_str().expect(PATH_TO_STRING_MSG)
+        )
+    })
+}
+
+fn read_json(source: &Path, file: &Path) -> Result<Value> {
+    let path = source.join(file);
+    let path = path.to_str().expect(PATH_TO_STRING_MSG);
+    trace!("reading configuration from '{path}'...");
+    let file = OpenOptions::new()
+        .read(true)
+        .open(path)
+        .with_context(|| format!("failed to load configuration from {path}"))?;
+    serde_json::from_reader(file)
+        .with_context(|| format!("failed to deserialize configuration file from {path}"))
+}
+
+/// Replaces any `include` entries of an attributes array with the attributes of the included file
+/// (either a single attribute or an array of attributes), overridden by any other fields of the
+/// entry.
+fn include_attributes(
+    source: &Path,
+    directory: &Path,
+    attributes: Vec<Value>,
+    loading: &mut Vec<PathBuf>,
+) -> Result<Vec<Value>> {
+    let mut included = Vec::with_capacity(attributes.len());
+    for mut attribute in attributes {
+        let Some(file) = attribute
+            .as_object_mut()
+            .and_then(|a| a.shift_remove(INCLUDE))
+        else {
+            included.push(attribute);
+            continue;
+        };
+        let file = directory.join(
+            file.as_str()
+                .ok_or_else(|| anyhow!("'{INCLUDE}' of an attribute must be a file name"))?,
+        );
+        let canonical = canonical(source, &file)?;
+        if loading.contains(&canonical) {
+            return Err(anyhow!(
+                "configuration file {} includes itself",
+                file.to_str().expect(PATH_TO_STRING_MSG)
+            ));
+        }
+        loading.push(canonical);
+
+        let file_directory = file.parent().unwrap_or_else(|| Path::new(""));
+        let mut attributes = match read_json(source, &file)? {
+            Value::Array(attributes) => attributes,
+            attribute => vec![attribute],
+        };
+        for attribute in &mut attributes {
+            rebase_attribute(attribute, file_directory);
+        }
+        for mut included_attribute in
+            include_attributes(source, file_directory, attributes, loading)?
+        {
+            merge(&mut included_attribute, attribute.clone());
+            included.push(included_attribute);
+        }
+        loading.pop();
+    }
+    Ok(included)
+}
+
+/// Merges the overriding value into the base value: objects are merged field by field (with
+/// attributes merged by name), whilst any other value is replaced.
+fn merge(base: &mut Value, overriding: Value) {
+    match (base, overriding) {
+        (Value::Object(base), Value::Object(overriding)) => {
+            for (name, value) in overriding {
+                match (name.as_str(), base.get_mut(&name), value) {
+                    (ATTRIBUTES, Some(Value::Array(attributes)), Value::Array(overriding)) => {
+                        for attribute in overriding {
+                            match attributes.iter_mut().find(|a| {
+                                a.get(NAME).is_some() && a.get(NAME) == attribute.get(NAME)
+                            }) {
+                                Some(existing) => merge(existing, attribute),
+                                None => attributes.push(attribute),
+                            }
+                        }
+                    }
+                    (_, Some(existing), value) => merge(existing, value),
+                    (_, None, value) => {
+                        base.insert(name, value);
+                    }
+                }
+            }
+        }
+        (base, overriding) => *base = overriding,
+    }
+}
+
+/// Resolves the relative paths of a configuration against its directory, within the source
+/// directory.
+fn rebase_config(config: &mut Value, directory: &Path) {
+    for pointer in ["/collection/image", "/collection/banner", "/hidden/image"] {
+        if let Some(path) = config.pointer_mut(pointer) {
+            rebase(path, directory);
+        }
+    }
+    if let Some(Value::Array(attributes)) = config.get_mut(ATTRIBUTES) {
+        for attribute in attributes {
+            rebase_attribute(attribute, directory);
+        }
+    }
+}
+
+fn rebase_attribute(attribute: &mut Value, directory: &Path) {
+    if let Some(Value::Object(options)) = attribute.get_mut(OPTIONS) {
+        for option in options.values_mut() {
+            for field in ["file", "font"] {
+                if let Some(path) = option.get_mut(field) {
+                    rebase(path, directory);
+                }
+            }
+            if let Some(Value::Array(parts)) = option.get_mut("parts") {
+                for path in parts.iter_mut().filter_map(|p| p.get_mut("file")) {
+                    rebase(path, directory);
+                }
+            }
+        }
+    }
+}
+
+fn rebase(path: &mut Value, directory: &Path) {
+    if let Value::String(file) = path {
+        if Path::new(file).is_relative() && directory != Path::new("") {
+            *file = directory
+                .join(&file)
+                .to_str()
+                .expect(PATH_TO_STRING_MSG)
+                .to_string();
+        }
+    }
+}
+
+/// Replaces any `${NAME}` within string values with the value of the named environment variable,
+/// where `$${` is a literal `${`.
+fn interpolate(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(string) => {
+            let mut position = 0;
+            while let Some(start) = string[position..].find("${").map(|i| position + i) {
+                if string[..start].ends_with('$') {
+                    // Remove the escaping `$`, continuing after the literal `${`
+                    string.remove(start - 1);
+                    position = start + 1;
+                    continue;
+                }
+                let end = string[start..]
+                    .find('}')
+                    .map(|end| start + end)
+                    .ok_or_else(|| anyhow!("unterminated environment variable in '{string}'"))?;
+                let name = &string[start + 2..end];
+                let variable = env::var(name).with_context(|| {
+                    format!(
+                        "environment variable '{name}' referenced by the configuration is not set"
+                    )
+                })?;
+                // Continue after the substituted value, which is not itself interpolated
+                position = start + variable.len();
+                string.replace_range(start..=end, &variable);
+            }
+        }
+        Value::Array(values) => values.iter_mut().try_for_each(interpolate)?,
+        Value::Object(fields) => fields.values_mut().try_for_each(interpolate)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// A configuration field set from the command line, overriding the value within the configuration
+/// file (e.g. `supply=100` or `collection.name=Test`).
+#[derive(Debug)]
+pub(crate) struct Override {
+    // The dot-separated path of the field, where array elements are referenced by index or name.
+    path: Vec<String>,
+    // The value of the field, parsed as JSON if valid (e.g. numbers) and otherwise as a string.
+    value: Value,
+}
+
+impl Override {
+    fn apply(&self, config: &mut Value) -> Result<()> {
+        let field = self.path.join(".");
+        let mut value = config;
+        for (depth, name) in self.path.iter().enumerate() {
+            value = match value {
+                // Only optional fields are added, so that misspelt fields are not silently ignored
+                Value::Object(fields)
+                    if fields.contains_key(name) || optional(&self.path[..depth], name) =>
+                {
+                    fields
+                        .entry(name.as_str())
+                        .or_insert_with(|| Value::Object(Map::new()))
+                }
+                Value::Object(_) => {
+                    return Err(anyhow!(
+                        "unable to set '{field}' as '{name}' could not be found"
+                    ))
+                }
+                Value::Array(values) => match name.parse::<usize>() {
+                    Ok(i) => values.get_mut(i),
+                    Err(_) => values
+                        .iter_mut()
+                        .find(|v| v.get(NAME).and_then(Value::as_str) == Some(name)),
+                }
+                .ok_or_else(|| anyhow!("unable to set '{field}' as '{name}' could not be found"))?,
+                _ => {
+                    return Err(anyhow!(
+                        "unable to set '{field}' as '{name}' is not an object"
+                    ))
+                }
+            };
+        }
+        trace!("setting '{field}' to {}", self.value);
+        *value = self.value.clone();
+        Ok(())
+    }
+}
+
+/// Whether the field is an optional field of its parent, and so may be set when absent.
+fn optional(parent: &[String], name: &str) -> bool {
+    OPTIONAL_FIELDS.iter().any(|(pattern, names)| {
+        let pattern: Vec<&str> = pattern.split('.').filter(|p| !p.is_empty()).collect();
+        pattern.len() == parent.len()
+            && pattern.iter().zip(parent).all(|(p, s)| *p == "*" || p == s)
+            && names.iter().any(|n| *n == "*" || *n == name)
+    })
+}
+
+impl FromStr for Override {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (path, value) = s.split_once('=').ok_or_else(|| {
+            anyhow!("expected a setting of the form 'field=value' but found '{s}'")
+        })?;
+        if path.is_empty() || path.split('.').any(str::is_empty) {
+            return Err(anyhow!("'{path}' is not a valid field"));
+        }
+        Ok(Self {
+            path: path.split('.').map(String::from).collect(),
+            value: serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string())),
+        })
+    }
+}
+
+/// An attribute value selected from the command line (e.g. `Hat=Crown`).
+#[derive(Debug)]
+pub(crate) struct Selection {
+    pub(crate) attribute: String,
+    pub(crate) value: String,
+}
+
+impl FromStr for Selection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (attribute, value) = s.split_once('=').ok_or_else(|| {
+            anyhow!("expected an attribute value of the form 'attribute=value' but found '{s}'")
+        })?;
+        Ok(Self {
+            attribute: attribute.to_string(),
+            value: value.to_string(),
+        })
+    }
+}
+
 #[derive(Deserialize)]
 pub(crate) struct Config {
     pub name: String,
@@ -44,16 +483,273 @@ pub(crate) struct Config {
     pub external_url: Option<String>,
     pub background_color: Option<Color>,
     pub attributes: Vec<Attribute>,
+    pub collection: Option<Collection>,
+    #[serde(default)]
+    pub metadata: CustomMetadata,
+    pub hidden: Option<Hidden>,
+    #[serde(default)]
+    pub editions: Vec<Edition>,
+    #[serde(default)]
+    pub edition_output: EditionOutput,
+    #[serde(default)]
+    pub media_format: MediaFormat,
+    #[serde(default)]
+    pub standard: Standard,
+    pub metaplex: Option<Metaplex>,
+    #[serde(default)]
+    pub erc1155: Erc1155,
+    #[serde(default)]
+    pub tzip21: Tzip21,
+    pub cip25: Option<Cip25>,
+    #[serde(default)]
+    pub video: Video,
+    /// The configuration as loaded, compared with a changed configuration whilst watching.
+    #[serde(skip)]
+    pub(crate) json: Value,
 }
 
 impl Config {
+    /// Whether any setting other than the attributes differs from the previous configuration.
+    pub(crate) fn settings_changed(&self, previous: &Config) -> bool {
+        let settings = |config: &Config| {
+            let mut settings = config.json.clone();
+            if let Some(settings) = settings.as_object_mut() {
+                settings.shift_remove(ATTRIBUTES);
+            }
+            settings
+        };
+        settings(self) != settings(previous)
+    }
+
+    /// Whether an option differs from the previous configuration, including the settings and
+    /// layer order of its attribute.
+    pub(crate) fn option_changed(&self, previous: &Config, attribute: &str, option: &str) -> bool {
+        let layer = |config: &Config| {
+            let position = config.attributes.iter().position(|a| a.name == attribute);
+            let mut attribute = config
+                .json
+                .get(ATTRIBUTES)
+                .and_then(Value::as_array)
+                .and_then(|attributes| {
+                    attributes
+                        .iter()
+                        .find(|a| a.get(NAME).and_then(Value::as_str) == Some(attribute))
+                })
+                .cloned()
+                .unwrap_or_default();
+            let option = attribute
+                .as_object_mut()
+                .and_then(|a| a.shift_remove(OPTIONS))
+                .and_then(|mut options| options.get_mut(option).map(Value::take));
+            (position, attribute, option)
+        };
+        layer(self) != layer(previous)
+    }
+
     pub(crate) fn validate(&self, path: &Path) -> Result<()> {
         debug!("validating configuration...");
 
+        if self.video.bitrate.is_some() && self.video.crf.is_some() {
+            return Err(anyhow!(
+                "only one of 'bitrate' or 'crf' can be specified for video encoding"
+            ));
+        }
+
+        if self.standard == Standard::Metaplex {
+            let metaplex = self.metaplex.as_ref().ok_or_else(|| {
+                anyhow!("a 'metaplex' section is required for the metaplex metadata standard")
+            })?;
+            if metaplex.seller_fee_basis_points > 10_000 {
+                return Err(anyhow!(
+                    "'seller_fee_basis_points' cannot be more than 10000 (i.e. 100%)"
+                ));
+            }
+            if !metaplex.creators.is_empty()
+                && metaplex
+                    .creators
+                    .iter()
+                    .map(|c| c.share as u32)
+                    .sum::<u32>()
+                    != 100
+            {
+                return Err(anyhow!("the shares of all creators must add up to 100"));
+            }
+        }
+
+        if self.standard == Standard::Cip25 && self.cip25.is_none() {
+            return Err(anyhow!(
+                "a 'cip25' section is required for the cip25 metadata standard"
+            ));
+        }
+
+        if self.standard == Standard::Cip25 && self.hidden.is_some() {
+            return Err(anyhow!(
+                "hidden metadata is not supported by the cip25 metadata standard"
+            ));
+        }
+
+        if self.media_format == MediaFormat::Svg
+            && self.attributes.iter().any(|a| {
+                a.options
+                    .values()
+                    .any(|o| matches!(o, AttributeOption::Audio { .. }))
+            })
+        {
+            return Err(anyhow!(
+                "audio cannot be combined with svg media - use a media format of 'png' for video"
+            ));
+        }
+
+        // Check attributes are either all or none ordered explicitly by z-index
+        if let Some(attribute) = self.attributes.iter().find(|a| a.z.is_none()) {
+            if self.attributes.iter().any(|a| a.z.is_some()) {
+                return Err(anyhow!(
+                    "attribute '{}' requires a z-index as other attributes specify a z-index",
+                    attribute.name
+                ));
+            }
+        }
+
+        // Check conditional options depend on known attribute values, without circular dependencies
+        self.sampling_order()?;
+        for attribute in &self.attributes {
+            for (option, conditions) in attribute.options.iter().map(|(k, o)| (k, o.when())) {
+                for (name, values) in conditions {
+                    let dependency = self
+                        .attributes
+                        .iter()
+                        .find(|a| &a.name == name)
+                        .expect("expected dependency to be validated");
+                    if let Some(value) =
+                        values.iter().find(|v| !dependency.options.contains_key(*v))
+                    {
+                        return Err(anyhow!(
+                            "option '{option}' of attribute '{}' depends on unknown value '{value}' of attribute '{name}'",
+                            attribute.name
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Check attributes merged into a single trait are not numeric
+        for (i, attribute) in self.attributes.iter().enumerate() {
+            if let Some(other) = self.attributes[..i]
+                .iter()
+                .find(|a| a.trait_type() == attribute.trait_type())
+            {
+                if attribute.display_type.is_some() || other.display_type.is_some() {
+                    return Err(anyhow!(
+                        "attributes '{}' and '{}' share the trait type '{}' so cannot have a display type",
+                        other.name,
+                        attribute.name,
+                        attribute.trait_type()
+                    ));
+                }
+            }
+        }
+
+        // Check numeric options can be displayed as configured
+        for attribute in &self.attributes {
+            for (value, option) in &attribute.options {
+                let (min, max) = match option {
+                    AttributeOption::Number { number, .. } => (*number, *number),
+                    AttributeOption::Range { min, max, .. } => (*min as f64, *max as f64),
+                    AttributeOption::None { .. } => continue,
+                    _ if attribute.display_type.is_some() => {
+                        return Err(anyhow!(
+                        "option '{value}' of attribute '{}' must be numeric to have a display type",
+                        attribute.name
+                    ))
+                    }
+                    _ => continue,
+                };
+                let whole = min.fract() == 0.0 && max.fract() == 0.0;
+                match attribute.display_type {
+                    None | Some(DisplayType::Number) if !whole || min < 0.0 => {
+                        return Err(anyhow!(
+                            "option '{value}' of attribute '{}' must be a positive whole number",
+                            attribute.name
+                        ))
+                    }
+                    Some(DisplayType::Date) if !whole => {
+                        return Err(anyhow!(
+                            "option '{value}' of attribute '{}' must be a unix timestamp (seconds)",
+                            attribute.name
+                        ))
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Check editions make up the supply, without sharing token numbers or output directories
+        if !self.editions.is_empty() {
+            let supply: usize = self.editions.iter().map(|e| e.supply).sum();
+            if supply != self.supply {
+                return Err(anyhow!(
+                    "the supply of {} does not match the total supply of {supply} across all editions (when setting 'supply', also set the 'supply' of each edition, e.g. `--set editions.0.supply=10`)",
+                    self.supply
+                ));
+            }
+        }
+        for (i, edition) in self.editions.iter().enumerate() {
+            if edition.name.is_empty() || edition.name.contains(['/', '\\']) {
+                return Err(anyhow!(
+                    "edition name '{}' must not be empty or contain a path separator",
+                    edition.name
+                ));
+            }
+            for other in &self.editions[..i] {
+                if other.name == edition.name {
+                    return Err(anyhow!("edition name '{}' is not unique", edition.name));
+                }
+                if self.edition_output == EditionOutput::Continuous
+                    && edition.tokens().start < other.tokens().end
+                    && other.tokens().start < edition.tokens().end
+                {
+                    return Err(anyhow!(
+                        "the tokens of editions '{}' and '{}' overlap",
+                        other.name,
+                        edition.name
+                    ));
+                }
+            }
+            for (name, weights) in &edition.weights {
+                let attribute = self
+                    .attributes
+                    .iter()
+                    .find(|a| &a.name == name)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "edition '{}' overrides the weights of unknown attribute '{name}'",
+                            edition.name
+                        )
+                    })?;
+                if let Some(option) = weights.keys().find(|o| !attribute.options.contains_key(*o)) {
+                    return Err(anyhow!(
+                        "edition '{}' overrides the weight of unknown option '{option}' of attribute '{name}'",
+                        edition.name
+                    ));
+                }
+            }
+        }
+
         // Check if configured paths exists
+        if let Some(collection) = &self.collection {
+            for file in [&collection.image, &collection.banner]
+                .into_iter()
+                .flatten()
+            {
+                Self::validate_path(&path.join(file))?;
+            }
+        }
+        if let Some(hidden) = &self.hidden {
+            Self::validate_path(&path.join(&hidden.image))?;
+        }
         for attribute in &self.attributes {
             for value in attribute.options.values() {
-                if let Some(file) = value.path() {
+                for file in value.paths() {
                     Self::validate_path(&path.join(file))?;
                 }
             }
@@ -62,7 +758,54 @@ impl Config {
         Ok(())
     }
 
-    fn validate_path(file: &PathBuf) -> Result<()> {
+    /// The order in which attributes are randomly chosen, such that each attribute is chosen after
+    /// any attributes its options depend upon.
+    pub(crate) fn sampling_order(&self) -> Result<Vec<&Attribute>> {
+        let mut order = Vec::with_capacity(self.attributes.len());
+        for attribute in &self.attributes {
+            self.visit(attribute, &mut order, &mut Vec::new())?;
+        }
+        Ok(order)
+    }
+
+    fn visit<'a>(
+        &'a self,
+        attribute: &'a Attribute,
+        order: &mut Vec<&'a Attribute>,
+        visiting: &mut Vec<&'a str>,
+    ) -> Result<()> {
+        if order.contains(&attribute) {
+            return Ok(());
+        }
+        if visiting.contains(&attribute.name.as_str()) {
+            return Err(anyhow!(
+                "the options of attribute '{}' have a circular dependency: {} -> {}",
+                attribute.name,
+                visiting.join(" -> "),
+                attribute.name
+            ));
+        }
+
+        visiting.push(&attribute.name);
+        for name in attribute.dependencies() {
+            let dependency = self
+                .attributes
+                .iter()
+                .find(|a| &a.name == name)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "the options of attribute '{}' depend on unknown attribute '{name}'",
+                        attribute.name
+                    )
+                })?;
+            self.visit(dependency, order, visiting)?;
+        }
+        visiting.pop();
+        order.push(attribute);
+        Ok(())
+    }
+
+    fn validate_path(file: &Path) -> Result<()> {
         let file_path = file.to_str().expect(PATH_TO_STRING_MSG);
         trace!("checking '{file_path}' file exists...");
         if !file.is_file() {
@@ -74,15 +817,307 @@ impl Config {
     }
 }
 
+/// The collection-level metadata, written as `collection.json` alongside the token metadata.
+#[derive(Deserialize)]
+pub(crate) struct Collection {
+    /// The name of the collection.
+    pub(crate) name: String,
+    /// A description of the collection, defaulting to the token description.
+    pub(crate) description: Option<String>,
+    /// The path to the image of the collection.
+    pub(crate) image: Option<PathBuf>,
+    /// The path to the banner image of the collection.
+    pub(crate) banner: Option<PathBuf>,
+    /// A url for the collection.
+    pub(crate) external_link: Option<String>,
+    /// The royalties for secondary sales, in basis points (i.e. 100 is 1%).
+    pub(crate) seller_fee_basis_points: Option<u16>,
+    /// The address to which royalties are paid.
+    pub(crate) fee_recipient: Option<String>,
+}
+
+/// The placeholder metadata of each token prior to reveal, written to the `hidden` directory.
+#[derive(Deserialize)]
+pub(crate) struct Hidden {
+    /// A name for the token, including {id} which will be replaced by the token number.
+    pub(crate) name: String,
+    /// A description of the token, defaulting to the token description.
+    pub(crate) description: Option<String>,
+    /// The path to the placeholder image shared by all tokens.
+    pub(crate) image: PathBuf,
+}
+
+/// An edition of the collection (e.g. a season), sharing the configured attributes.
+#[derive(Deserialize)]
+pub(crate) struct Edition {
+    /// The name of the edition, also used as its output directory name.
+    pub(crate) name: String,
+    /// The number of tokens in the edition.
+    pub(crate) supply: usize,
+    /// The number of the first token, defaulting to follow on from the previous edition.
+    pub(crate) start_token: Option<usize>,
+    /// A name for the tokens of the edition, defaulting to the configured name.
+    pub(crate) token_name: Option<String>,
+    /// Option weights overriding those configured, by attribute name and then option name.
+    #[serde(default)]
+    pub(crate) weights: Weights,
+}
+
+impl Edition {
+    /// The token numbers of the edition.
+    pub(crate) fn tokens(&self) -> Range<usize> {
+        let start_token = self
+            .start_token
+            .expect("expected edition start token to be resolved when loaded");
+        start_token..start_token + self.supply
+    }
+}
+
+/// Option weights by attribute name and then option name.
+pub(crate) type Weights = IndexMap<String, IndexMap<String, f64>>;
+
+/// How the tokens of each edition are output.
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum EditionOutput {
+    /// All editions are output together, within a single range of token numbers.
+    #[default]
+    Continuous,
+    /// Each edition is output to its own directory within the output directory.
+    Separate,
+}
+
+/// The format of the resulting token media.
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MediaFormat {
+    /// All layers are rasterized into a single PNG image.
+    #[default]
+    Png,
+    /// All layers are composed into a single SVG document, with raster layers embedded as data URIs.
+    Svg,
+}
+
+/// The standard the resulting token metadata conforms to.
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Standard {
+    /// OpenSea/ERC-721 metadata, named by token number.
+    #[default]
+    OpenSea,
+    /// Metaplex (Solana) token metadata, named as `{n}.json` for Candy Machine.
+    Metaplex,
+    /// ERC-1155 metadata, named by the zero-padded hex token id.
+    Erc1155,
+    /// Tezos TZIP-21 rich metadata (e.g. objkt), named by token number.
+    Tzip21,
+    /// Cardano CIP-25 metadata, written as a single combined `721` policy map.
+    Cip25,
+}
+
+/// The Tezos TZIP-21 specific fields of the resulting token metadata.
+#[derive(Default, Deserialize)]
+pub(crate) struct Tzip21 {
+    /// A short symbol for the tokens.
+    pub(crate) symbol: Option<String>,
+    /// The creators of the tokens, typically wallet addresses.
+    #[serde(default)]
+    pub(crate) creators: Vec<String>,
+    /// A statement about the rights of the tokens.
+    pub(crate) rights: Option<String>,
+    /// A list of tags that describe the tokens.
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+}
+
+/// The Cardano CIP-25 specific fields of the resulting metadata.
+#[derive(Deserialize)]
+pub(crate) struct Cip25 {
+    /// The policy id of the minting policy.
+    pub(crate) policy_id: String,
+    /// The asset name, including {id} which will be replaced by the token number.
+    pub(crate) asset_name: String,
+    /// The version of the CIP-25 standard.
+    #[serde(default = "cip25_version_default")]
+    pub(crate) version: String,
+}
+
+fn cip25_version_default() -> String {
+    "1.0".to_string()
+}
+
+/// Customisation of the resulting token metadata, applied to the metadata of any standard.
+#[derive(Deserialize)]
+pub(crate) struct CustomMetadata {
+    /// Whether the dna of each token is included, where the standard allows additional fields.
+    #[serde(default = "metadata_default")]
+    pub(crate) dna: bool,
+    /// Additional top-level fields, with string values templated using the token variables. A
+    /// `null` value removes the field.
+    #[serde(default)]
+    pub(crate) fields: serde_json::Map<String, serde_json::Value>,
+    /// Top-level fields to rename, keyed by the original field name.
+    #[serde(default)]
+    pub(crate) rename: IndexMap<String, String>,
+    /// The order of top-level fields (by resulting name), with any remaining fields following.
+    #[serde(default)]
+    pub(crate) order: Vec<String>,
+}
+
+impl Default for CustomMetadata {
+    fn default() -> Self {
+        Self {
+            dna: true,
+            fields: Default::default(),
+            rename: Default::default(),
+            order: Default::default(),
+        }
+    }
+}
+
+/// The ERC-1155 specific fields of the resulting token metadata.
+#[derive(Default, Deserialize)]
+pub(crate) struct Erc1155 {
+    /// The number of decimal places that the token amount should display.
+    pub(crate) decimals: Option<u8>,
+    /// Any additional properties included with each token.
+    #[serde(default)]
+    pub(crate) properties: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The Metaplex specific fields of the resulting token metadata.
+#[derive(Deserialize)]
+pub(crate) struct Metaplex {
+    /// The symbol of the collection.
+    pub(crate) symbol: String,
+    /// The royalties awarded to creators, in basis points (i.e. 500 is 5%).
+    #[serde(default)]
+    pub(crate) seller_fee_basis_points: u16,
+    /// The creators and their share of royalties.
+    #[serde(default)]
+    pub(crate) creators: Vec<Creator>,
+    /// The collection the tokens belong to.
+    pub(crate) collection: Option<MetaplexCollection>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Creator {
+    /// The wallet address of the creator.
+    pub(crate) address: String,
+    /// The percentage share of royalties.
+    pub(crate) share: u8,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct MetaplexCollection {
+    pub(crate) name: String,
+    pub(crate) family: Option<String>,
+}
+
+/// The encoding profile used when combining images with audio to create video.
+#[derive(Deserialize)]
+#[serde(default)]
+pub(crate) struct Video {
+    /// The container format, used as the extension of the resulting video file.
+    pub(crate) container: String,
+    /// The ffmpeg video codec.
+    pub(crate) video_codec: String,
+    /// The ffmpeg audio codec.
+    pub(crate) audio_codec: String,
+    /// The target video bitrate (e.g. 2M), which cannot be combined with `crf`.
+    pub(crate) bitrate: Option<String>,
+    /// The constant rate factor, which cannot be combined with `bitrate`.
+    pub(crate) crf: Option<u8>,
+    /// The encoder preset (e.g. medium, slow).
+    pub(crate) preset: Option<String>,
+    /// The resolution to scale the image to, as an ffmpeg scale expression (e.g. 1080:-2).
+    pub(crate) scale: Option<String>,
+    /// The target audio bitrate (e.g. 192k).
+    pub(crate) audio_bitrate: Option<String>,
+    /// The pixel format.
+    pub(crate) pixel_format: String,
+    /// The frame rate of the still image input.
+    pub(crate) framerate: u32,
+    /// The colorspace of the still image input.
+    pub(crate) colorspace: String,
+    /// Any additional output options passed to ffmpeg, keyed by option name without the leading
+    /// '-', with a null value for options which do not take a value.
+    pub(crate) args: IndexMap<String, Option<String>>,
+}
+
+impl Default for Video {
+    fn default() -> Self {
+        Self {
+            container: "mp4".to_string(),
+            video_codec: "libx264".to_string(),
+            audio_codec: "aac".to_string(),
+            bitrate: None,
+            crf: None,
+            preset: None,
+            scale: None,
+            audio_bitrate: None,
+            // Required for compatibility
+            pixel_format: "yuv420p".to_string(),
+            // Single image so only single frame
+            framerate: 1,
+            // Preserve colors as best as possible
+            colorspace: "bt709".to_string(),
+            args: IndexMap::new(),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub(crate) struct Attribute {
     /// The name of the attribute, as it should appear in the resulting token metadata.
     pub(crate) name: String,
+    /// The name of the trait in the resulting token metadata, defaulting to the attribute name.
+    /// Attributes sharing a trait type are merged into a single trait.
+    pub(crate) trait_type: Option<String>,
+    /// The z-index of the attribute within the layer stack (i.e. layer 0 being the bottom). When
+    /// specified, attributes are rendered by z-index rather than in reverse configuration order.
+    #[serde(alias = "layer")]
+    pub(crate) z: Option<usize>,
     /// The possible values for the attribute.
     pub(crate) options: IndexMap<String, AttributeOption>,
     /// Whether the attribute should be included in the resulting token metadata.
     #[serde(default = "metadata_default")]
     pub(crate) metadata: bool,
+    /// The name of an optional numeric trait holding the duration (in seconds) of the chosen audio.
+    pub(crate) duration: Option<String>,
+    /// How numeric option values are displayed in the resulting token metadata.
+    pub(crate) display_type: Option<DisplayType>,
+    /// The maximum value of numeric option values, displayed in the resulting token metadata.
+    pub(crate) max_value: Option<usize>,
+}
+
+/// How a numeric attribute is displayed in the resulting token metadata.
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DisplayType {
+    Number,
+    BoostPercentage,
+    BoostNumber,
+    /// A unix timestamp (seconds).
+    Date,
+}
+
+impl Attribute {
+    /// The names of the attributes which the options of the attribute depend upon.
+    pub(crate) fn dependencies(&self) -> Vec<&String> {
+        let mut dependencies = Vec::new();
+        for name in self.options.values().flat_map(|o| o.when().keys()) {
+            if !dependencies.contains(&name) {
+                dependencies.push(name);
+            }
+        }
+        dependencies
+    }
+
+    /// The name of the trait in the resulting token metadata.
+    pub(crate) fn trait_type(&self) -> &str {
+        self.trait_type.as_deref().unwrap_or(&self.name)
+    }
 }
 
 impl Hash for Attribute {
@@ -100,7 +1135,7 @@ impl PartialEq<Self> for Attribute {
 impl Eq for Attribute {}
 
 fn metadata_default() -> bool {
-    return true;
+    true
 }
 
 #[derive(Debug)]
@@ -110,16 +1145,38 @@ pub(crate) enum AttributeOption {
         file: PathBuf,
         /// The weighting for the option.
         weight: f64,
+        /// The value displayed in the resulting token metadata, defaulting to the option name.
+        value: Option<String>,
+        /// The values of other attributes required for the option to be chosen.
+        when: Conditions,
     },
     Color {
         color: Color,
         /// The weighting for the option.
         weight: f64,
+        /// The value displayed in the resulting token metadata, defaulting to the option name.
+        value: Option<String>,
+        /// The values of other attributes required for the option to be chosen.
+        when: Conditions,
     },
     Image {
         file: PathBuf,
         /// The weighting for the option.
         weight: f64,
+        /// The value displayed in the resulting token metadata, defaulting to the option name.
+        value: Option<String>,
+        /// The values of other attributes required for the option to be chosen.
+        when: Conditions,
+    },
+    Svg {
+        /// The path to the svg file to be used, which is rasterized at the image resolution.
+        file: PathBuf,
+        /// The weighting for the option.
+        weight: f64,
+        /// The value displayed in the resulting token metadata, defaulting to the option name.
+        value: Option<String>,
+        /// The values of other attributes required for the option to be chosen.
+        when: Conditions,
     },
     Text {
         /// The path to the font to be used.
@@ -134,21 +1191,94 @@ pub(crate) enum AttributeOption {
         color: Color,
         /// The weighting for the option.
         weight: f64,
+        /// The value displayed in the resulting token metadata, defaulting to the option name.
+        value: Option<String>,
+        /// The values of other attributes required for the option to be chosen.
+        when: Conditions,
+    },
+    Number {
+        /// The numeric value of the option.
+        number: f64,
+        /// The weighting for the option.
+        weight: f64,
+        /// The value displayed in the resulting token metadata, defaulting to the option name.
+        value: Option<String>,
+        /// The values of other attributes required for the option to be chosen.
+        when: Conditions,
+    },
+    Range {
+        /// The minimum numeric value, randomly drawn per token.
+        min: i64,
+        /// The maximum numeric value (inclusive), randomly drawn per token.
+        max: i64,
+        /// The weighting for the option.
+        weight: f64,
+        /// The value displayed in the resulting token metadata, defaulting to the option name.
+        value: Option<String>,
+        /// The values of other attributes required for the option to be chosen.
+        when: Conditions,
+    },
+    Parts {
+        /// The image parts of the option, each placed at its own z-index within the layer stack.
+        parts: Vec<Part>,
+        /// The weighting for the option.
+        weight: f64,
+        /// The value displayed in the resulting token metadata, defaulting to the option name.
+        value: Option<String>,
+        /// The values of other attributes required for the option to be chosen.
+        when: Conditions,
     },
     None {
         /// The weighting for the option.
         weight: f64,
+        /// The value displayed in the resulting token metadata, defaulting to the option name.
+        value: Option<String>,
+        /// The values of other attributes required for the option to be chosen.
+        when: Conditions,
     },
 }
 
 impl AttributeOption {
-    pub(crate) fn path(&self) -> Option<&PathBuf> {
+    pub(crate) fn paths(&self) -> Vec<&PathBuf> {
         match self {
-            AttributeOption::Audio { file, .. } => Some(file),
-            AttributeOption::Color { .. } => None,
-            AttributeOption::Image { file, .. } => Some(file),
-            AttributeOption::Text { font, .. } => Some(font),
-            AttributeOption::None { .. } => None,
+            AttributeOption::Audio { file, .. } => vec![file],
+            AttributeOption::Color { .. } => vec![],
+            AttributeOption::Image { file, .. } => vec![file],
+            AttributeOption::Svg { file, .. } => vec![file],
+            AttributeOption::Text { font, .. } => vec![font],
+            AttributeOption::Number { .. } => vec![],
+            AttributeOption::Range { .. } => vec![],
+            AttributeOption::Parts { parts, .. } => parts.iter().map(|p| &p.file).collect(),
+            AttributeOption::None { .. } => vec![],
+        }
+    }
+
+    pub(crate) fn value(&self) -> Option<&str> {
+        match self {
+            AttributeOption::Audio { value, .. } => value.as_deref(),
+            AttributeOption::Color { value, .. } => value.as_deref(),
+            AttributeOption::Image { value, .. } => value.as_deref(),
+            AttributeOption::Svg { value, .. } => value.as_deref(),
+            AttributeOption::Text { value, .. } => value.as_deref(),
+            AttributeOption::Number { value, .. } => value.as_deref(),
+            AttributeOption::Range { value, .. } => value.as_deref(),
+            AttributeOption::Parts { value, .. } => value.as_deref(),
+            AttributeOption::None { value, .. } => value.as_deref(),
+        }
+    }
+
+    /// The values of other attributes required for the option to be chosen.
+    pub(crate) fn when(&self) -> &Conditions {
+        match self {
+            AttributeOption::Audio { when, .. } => when,
+            AttributeOption::Color { when, .. } => when,
+            AttributeOption::Image { when, .. } => when,
+            AttributeOption::Svg { when, .. } => when,
+            AttributeOption::Text { when, .. } => when,
+            AttributeOption::Number { when, .. } => when,
+            AttributeOption::Range { when, .. } => when,
+            AttributeOption::Parts { when, .. } => when,
+            AttributeOption::None { when, .. } => when,
         }
     }
 
@@ -157,7 +1287,11 @@ impl AttributeOption {
             AttributeOption::Audio { weight, .. } => weight,
             AttributeOption::Color { weight, .. } => weight,
             AttributeOption::Image { weight, .. } => weight,
+            AttributeOption::Svg { weight, .. } => weight,
             AttributeOption::Text { weight, .. } => weight,
+            AttributeOption::Number { weight, .. } => weight,
+            AttributeOption::Range { weight, .. } => weight,
+            AttributeOption::Parts { weight, .. } => weight,
             AttributeOption::None { weight, .. } => weight,
         }
     }
@@ -179,7 +1313,13 @@ impl<'de> Deserialize<'de> for AttributeOption {
                 let mut file = None;
                 let mut font = None;
                 let mut height = None;
+                let mut max = None;
+                let mut min = None;
+                let mut number = None;
+                let mut parts = None;
                 let mut text = None;
+                let mut value = None;
+                let mut when = None;
                 let mut x = None;
                 let mut y = None;
                 let mut weight = None;
@@ -217,12 +1357,48 @@ impl<'de> Deserialize<'de> for AttributeOption {
                             }
                             height = Some(map.next_value()?);
                         }
+                        "max" => {
+                            if max.is_some() {
+                                return Err(de::Error::duplicate_field("max"));
+                            }
+                            max = Some(map.next_value()?);
+                        }
+                        "min" => {
+                            if min.is_some() {
+                                return Err(de::Error::duplicate_field("min"));
+                            }
+                            min = Some(map.next_value()?);
+                        }
+                        "number" => {
+                            if number.is_some() {
+                                return Err(de::Error::duplicate_field("number"));
+                            }
+                            number = Some(map.next_value()?);
+                        }
+                        "parts" => {
+                            if parts.is_some() {
+                                return Err(de::Error::duplicate_field("parts"));
+                            }
+                            parts = Some(map.next_value::<Vec<Part>>()?);
+                        }
                         "text" => {
                             if text.is_some() {
                                 return Err(de::Error::duplicate_field("text"));
                             }
                             text = Some(map.next_value()?);
                         }
+                        "value" => {
+                            if value.is_some() {
+                                return Err(de::Error::duplicate_field("value"));
+                            }
+                            value = Some(map.next_value()?);
+                        }
+                        "when" => {
+                            if when.is_some() {
+                                return Err(de::Error::duplicate_field("when"));
+                            }
+                            when = Some(map.next_value()?);
+                        }
                         "x" => {
                             if x.is_some() {
                                 return Err(de::Error::duplicate_field("x"));
@@ -246,17 +1422,59 @@ impl<'de> Deserialize<'de> for AttributeOption {
                     }
                 }
 
+                let when = when.unwrap_or_default();
+
                 // Decide on type based on specified files
-                if let Some(file) = file {
+                if let Some(parts) = parts {
+                    if parts.is_empty() {
+                        return Err(de::Error::custom("at least one part is required"));
+                    }
+                    for part in &parts {
+                        let extension = part.file.extension().and_then(|e| e.to_str());
+                        if !extension.is_some_and(|e| {
+                            e.eq_ignore_ascii_case(SVG_EXTENSION)
+                                || ImageFormat::from_extension(e).is_some()
+                        }) {
+                            return Err(de::Error::custom(format!(
+                                "part '{}' is not a supported image",
+                                part.file.to_str().expect(PATH_TO_STRING_MSG)
+                            )));
+                        }
+                    }
+                    let weight = weight.unwrap_or(DEFAULT_WEIGHT);
+                    return Ok(AttributeOption::Parts {
+                        parts,
+                        weight,
+                        value,
+                        when,
+                    });
+                } else if let Some(file) = file {
                     let extension = file.extension().map(|e| e.to_ascii_lowercase());
                     return match extension.as_ref().and_then(|e| e.to_str()) {
                         Some(extension) => {
                             let weight = weight.unwrap_or(DEFAULT_WEIGHT);
                             if SUPPORTED_AUDIO_EXTENSIONS.contains(&extension) {
-                                Ok(AttributeOption::Audio { file, weight })
+                                Ok(AttributeOption::Audio {
+                                    file,
+                                    weight,
+                                    value,
+                                    when,
+                                })
+                            } else if extension == SVG_EXTENSION {
+                                Ok(AttributeOption::Svg {
+                                    file,
+                                    weight,
+                                    value,
+                                    when,
+                                })
                                 // Use supported extensions from underlying image library
-                            } else if let Some(_) = ImageFormat::from_extension(&extension) {
-                                Ok(AttributeOption::Image { file, weight })
+                            } else if ImageFormat::from_extension(extension).is_some() {
+                                Ok(AttributeOption::Image {
+                                    file,
+                                    weight,
+                                    value,
+                                    when,
+                                })
                             } else {
                                 Err(de::Error::custom(format!(
                                     "file extension {extension} not supported"
@@ -280,23 +1498,81 @@ impl<'de> Deserialize<'de> for AttributeOption {
                         y,
                         color,
                         weight,
+                        value,
+                        when,
                     });
                 } else if let Some(color) = color {
                     let weight = weight.unwrap_or(DEFAULT_WEIGHT);
-                    return Ok(AttributeOption::Color { color, weight });
-                } else if let Some(weight) = weight {
-                    return Ok(AttributeOption::None { weight });
+                    return Ok(AttributeOption::Color {
+                        color,
+                        weight,
+                        value,
+                        when,
+                    });
+                } else if let Some(number) = number {
+                    let weight = weight.unwrap_or(DEFAULT_WEIGHT);
+                    return Ok(AttributeOption::Number {
+                        number,
+                        weight,
+                        value,
+                        when,
+                    });
+                } else if min.is_some() || max.is_some() {
+                    let min = min.ok_or_else(|| de::Error::missing_field("min"))?;
+                    let max = max.ok_or_else(|| de::Error::missing_field("max"))?;
+                    if min > max {
+                        return Err(de::Error::custom(format!(
+                            "min of {min} is greater than max of {max}"
+                        )));
+                    }
+                    let weight = weight.unwrap_or(DEFAULT_WEIGHT);
+                    return Ok(AttributeOption::Range {
+                        min,
+                        max,
+                        weight,
+                        value,
+                        when,
+                    });
+                } else if weight.is_some() || value.is_some() {
+                    let weight = weight.unwrap_or(DEFAULT_WEIGHT);
+                    return Ok(AttributeOption::None {
+                        weight,
+                        value,
+                        when,
+                    });
                 }
 
                 Err(de::Error::custom("unable to determine attribute option"))
             }
         }
 
-        const FIELDS: &'static [&'static str] = &["color", "file", "weight"];
+        const FIELDS: &[&str] = &["color", "file", "value", "weight", "when"];
         deserializer.deserialize_struct("AttributeOption", FIELDS, AttributeOptionVisitor)
     }
 }
 
+/// The values of other attributes required for an option to be chosen, keyed by attribute name.
+pub(crate) type Conditions = IndexMap<String, Vec<String>>;
+
+/// An image part of a multi-part option, placed at its own z-index within the layer stack.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Part {
+    /// The path to the image (or svg) file to be used.
+    pub(crate) file: PathBuf,
+    /// The z-index of the part within the layer stack (i.e. layer 0 being the bottom), defaulting
+    /// to the layer of the attribute. A part is placed above the attribute at the same z-index.
+    pub(crate) z: Option<usize>,
+}
+
+impl Part {
+    /// Whether the part is a vector (svg) image.
+    pub(crate) fn vector(&self) -> bool {
+        self.file
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case(SVG_EXTENSION))
+    }
+}
+
 #[derive(Debug)]
 pub struct Color {
     pub(crate) hex: String,
@@ -349,3 +1625,283 @@ impl<'de> Deserialize<'de> for Color {
         deserializer.deserialize_str(ColorVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Creates a uniquely named source directory within the temporary directory, containing the
+    /// configuration files.
+    fn source(files: &[(&str, Value)]) -> PathBuf {
+        let source = env::temp_dir().join(format!("ng-config-{:08x}", rand::random::<u32>()));
+        for (file, config) in files {
+            let path = source.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, config.to_string()).unwrap();
+        }
+        source
+    }
+
+    fn read_config(files: &[(&str, Value)]) -> Result<Value> {
+        let source = source(files);
+        let config = read(&source, Path::new(files[0].0), &mut Vec::new());
+        std::fs::remove_dir_all(source).unwrap();
+        config
+    }
+
+    fn set(config: &mut Value, setting: &str) -> Result<()> {
+        setting.parse::<Override>()?.apply(config)
+    }
+
+    #[test]
+    fn interpolate_escaped() {
+        let mut value = json!(["$${NG_TEST_UNSET}", "a $$${b}", "$$"]);
+        interpolate(&mut value).unwrap();
+        assert_eq!(value, json!(["${NG_TEST_UNSET}", "a $${b}", "$$"]));
+    }
+
+    #[test]
+    fn interpolate_variables() {
+        env::set_var("NG_TEST_SITE", "https://example.com/${NG_TEST_SITE}");
+        let mut value = json!({ "external_url": "${NG_TEST_SITE}/{id}", "supply": 10 });
+        interpolate(&mut value).unwrap();
+        // Substituted values are not themselves interpolated
+        assert_eq!(
+            value,
+            json!({ "external_url": "https://example.com/${NG_TEST_SITE}/{id}", "supply": 10 })
+        );
+    }
+
+    #[test]
+    fn interpolate_unset() {
+        let mut value = json!({ "name": "${NG_TEST_UNSET}" });
+        let error = interpolate(&mut value).unwrap_err();
+        assert!(format!("{error:#}").contains("'NG_TEST_UNSET'"));
+    }
+
+    #[test]
+    fn interpolate_unterminated() {
+        let mut value = json!({ "name": "a ${NG_TEST_UNSET" });
+        let error = interpolate(&mut value).unwrap_err();
+        assert!(error.to_string().contains("unterminated"));
+    }
+
+    #[test]
+    fn override_values() {
+        let setting: Override = "collection.name=Test".parse().unwrap();
+        assert_eq!(setting.path, ["collection", "name"]);
+        assert_eq!(setting.value, json!("Test"));
+        assert_eq!("supply=20".parse::<Override>().unwrap().value, json!(20));
+        assert_eq!("a.b=x=y".parse::<Override>().unwrap().value, json!("x=y"));
+        assert!("supply".parse::<Override>().is_err());
+        assert!("=20".parse::<Override>().is_err());
+        assert!("a..b=20".parse::<Override>().is_err());
+    }
+
+    #[test]
+    fn override_attributes() {
+        let mut config = json!({
+            "attributes": [
+                { "name": "Background", "options": { "Grey": { "color": "#808080" } } },
+                { "name": "Hat", "options": { "Red": { "file": "red.png", "weight": 1 } } }
+            ]
+        });
+        set(&mut config, "attributes.1.options.Red.weight=5").unwrap();
+        set(
+            &mut config,
+            "attributes.Background.options.Grey.color=#000000",
+        )
+        .unwrap();
+        assert_eq!(
+            config["attributes"][1]["options"]["Red"]["weight"],
+            json!(5)
+        );
+        assert_eq!(
+            config["attributes"][0]["options"]["Grey"]["color"],
+            json!("#000000")
+        );
+        assert!(set(&mut config, "attributes.2.name=Body").is_err());
+        assert!(set(&mut config, "attributes.Body.name=Body").is_err());
+    }
+
+    #[test]
+    fn override_unknown() {
+        let mut config = json!({
+            "supply": 10,
+            "attributes": [{ "name": "Hat", "options": { "Red": { "file": "red.png" } } }]
+        });
+        let error = set(&mut config, "suply=20").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unable to set 'suply' as 'suply' could not be found"
+        );
+        assert!(set(&mut config, "video.crff=20").is_err());
+        assert!(set(&mut config, "attributes.Hat.options.Red.wieght=2").is_err());
+        assert!(set(&mut config, "supply.total=20").is_err());
+        assert_eq!(config["supply"], json!(10));
+    }
+
+    #[test]
+    fn override_optional() {
+        let mut config = json!({
+            "attributes": [{ "name": "Hat", "options": {
+                "Cape": { "parts": [{ "file": "back.png" }, { "file": "front.png" }] }
+            } }]
+        });
+        set(&mut config, "video.crf=20").unwrap();
+        set(&mut config, "metadata.fields.artist=Someone").unwrap();
+        set(&mut config, "attributes.Hat.z=2").unwrap();
+        set(&mut config, "attributes.Hat.options.Cape.parts.1.z=3").unwrap();
+        assert_eq!(config["video"], json!({ "crf": 20 }));
+        assert_eq!(config["metadata"]["fields"], json!({ "artist": "Someone" }));
+        assert_eq!(config["attributes"][0]["z"], json!(2));
+        assert_eq!(
+            config["attributes"][0]["options"]["Cape"]["parts"][1]["z"],
+            json!(3)
+        );
+    }
+
+    #[test]
+    fn optional_fields() {
+        let path = |path: &str| -> Vec<String> { path.split('.').map(String::from).collect() };
+        assert!(optional(&[], "video"));
+        assert!(!optional(&[], "supply"));
+        assert!(optional(&path("editions.0"), "token_name"));
+        assert!(optional(&path("editions.0.weights.Hat"), "Red"));
+        assert!(optional(&path("attributes.Hat.options.Red"), "weight"));
+        assert!(!optional(&path("attributes.Hat.options.Red"), "file"));
+        assert!(!optional(&path("attributes.Hat"), "weight"));
+    }
+
+    #[test]
+    fn merge_attributes_by_name() {
+        let mut base = json!({
+            "name": "Base",
+            "supply": 10,
+            "attributes": [
+                { "name": "Background", "options": { "Grey": { "color": "#808080" } } },
+                { "name": "Hat", "options": { "Red": { "file": "red.png" } } }
+            ]
+        });
+        merge(
+            &mut base,
+            json!({
+                "supply": 20,
+                "attributes": [
+                    { "name": "Hat", "options": { "Blue": { "file": "blue.png" } } },
+                    { "name": "Body", "options": { "Default": { "file": "body.png" } } }
+                ]
+            }),
+        );
+        assert_eq!(
+            base,
+            json!({
+                "name": "Base",
+                "supply": 20,
+                "attributes": [
+                    { "name": "Background", "options": { "Grey": { "color": "#808080" } } },
+                    { "name": "Hat", "options": {
+                        "Red": { "file": "red.png" },
+                        "Blue": { "file": "blue.png" }
+                    } },
+                    { "name": "Body", "options": { "Default": { "file": "body.png" } } }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn extends_rebased() {
+        let config = read_config(&[
+            (
+                "config.json",
+                json!({ "extends": "base/config.json", "name": "Test" }),
+            ),
+            (
+                "base/config.json",
+                json!({
+                    "name": "Base",
+                    "hidden": { "image": "hidden.png" },
+                    "attributes": [
+                        { "include": "hats/hat.json", "name": "Hat" },
+                        { "name": "Body", "options": { "Default": { "file": "/body.png" } } }
+                    ]
+                }),
+            ),
+            (
+                "base/hats/hat.json",
+                json!({ "name": "Headwear", "options": {
+                    "Red": { "file": "red.png" },
+                    "Cape": { "parts": [{ "file": "cape.png" }] }
+                } }),
+            ),
+        ])
+        .unwrap();
+        assert_eq!(
+            config,
+            json!({
+                "name": "Test",
+                "hidden": { "image": "base/hidden.png" },
+                "attributes": [
+                    { "name": "Hat", "options": {
+                        "Red": { "file": "base/hats/red.png" },
+                        "Cape": { "parts": [{ "file": "base/hats/cape.png" }] }
+                    } },
+                    { "name": "Body", "options": { "Default": { "file": "/body.png" } } }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn extends_cycle() {
+        let error = read_config(&[
+            ("config.json", json!({ "extends": "sub/base.json" })),
+            (
+                "sub/base.json",
+                json!({ "extends": "../sub/../config.json" }),
+            ),
+        ])
+        .unwrap_err();
+        assert!(error.to_string().starts_with(
+            "configuration files have a circular reference: config.json -> sub/base.json ->"
+        ));
+    }
+
+    #[test]
+    fn extends_self() {
+        let error =
+            read_config(&[("config.json", json!({ "extends": "./config.json" }))]).unwrap_err();
+        assert!(error.to_string().contains("circular reference"));
+    }
+
+    #[test]
+    fn extends_shared() {
+        // A file extended more than once without a cycle is not a circular reference
+        let config = read_config(&[
+            (
+                "config.json",
+                json!({ "extends": ["a.json", "b.json"], "supply": 3 }),
+            ),
+            ("a.json", json!({ "extends": "shared.json", "name": "A" })),
+            ("b.json", json!({ "extends": "shared.json" })),
+            ("shared.json", json!({ "name": "Shared", "supply": 1 })),
+        ])
+        .unwrap();
+        assert_eq!(config, json!({ "name": "Shared", "supply": 3 }));
+    }
+
+    #[test]
+    fn include_cycle() {
+        let error = read_config(&[
+            (
+                "config.json",
+                json!({ "attributes": [{ "include": "hat.json" }] }),
+            ),
+            ("hat.json", json!([{ "include": "hat.json" }])),
+        ])
+        .unwrap_err();
+        assert!(error.to_string().contains("includes itself"));
+    }
+}
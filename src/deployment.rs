@@ -1,17 +1,55 @@
-use crate::PATH_TO_STRING_MSG;
+use crate::metadata::{cip25, COLLECTION_FILE_NAME, HIDDEN_DIRECTORY};
+use crate::{output, PATH_TO_STRING_MSG};
 use anyhow::{Context, Error, Result};
 use log::trace;
 use serde_json::Value;
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::Path;
 use std::str::FromStr;
 use url::{ParseError, Url};
 
-pub(crate) fn deploy(source: &PathBuf, output: &str, metadata: &str, base_uri: &Url) -> Result<()> {
-    let metadata_path = source.join(output).join(metadata);
+// The length of the zero-padded hex token id used to name ERC-1155 metadata
+const ERC1155_ID_LENGTH: usize = 64;
+// The key of the policy map within combined CIP-25 metadata
+const CIP25_POLICIES: &str = "721";
+// The fields of token metadata which reference media
+const URL_FIELDS: [&str; 6] = [
+    "image",
+    "banner_image",
+    "animation_url",
+    "artifactUri",
+    "displayUri",
+    "thumbnailUri",
+];
+// The arrays of token metadata containing media, along with the field referencing the media
+const URL_ARRAY_FIELDS: [(&str, &str); 3] = [
+    ("/properties/files", "uri"),
+    ("/formats", "uri"),
+    ("/files", "src"),
+];
 
-    for file in fs::read_dir(&metadata_path).with_context(|| {
+pub(crate) fn deploy(source: &Path, output: &str, metadata: &str, base_uri: &Url) -> Result<()> {
+    output::check_editions(source, output)?;
+    deploy_directory(&source.join(output).join(metadata), base_uri)?;
+
+    // Update hidden metadata (if applicable)
+    let hidden_path = source.join(output).join(HIDDEN_DIRECTORY);
+    if hidden_path.is_dir() {
+        deploy_directory(&hidden_path, base_uri)?;
+    }
+
+    // Update collection metadata (if applicable)
+    let collection_path = source.join(output).join(COLLECTION_FILE_NAME);
+    if collection_path.is_file() {
+        deploy_file(&collection_path, base_uri)?;
+    }
+
+    Ok(())
+}
+
+fn deploy_directory(metadata_path: &Path, base_uri: &Url) -> Result<()> {
+    for file in fs::read_dir(metadata_path).with_context(|| {
         format!(
             "unable to read metadata from {}",
             &metadata_path.to_str().expect(PATH_TO_STRING_MSG)
@@ -19,48 +57,108 @@ pub(crate) fn deploy(source: &PathBuf, output: &str, metadata: &str, base_uri: &
     })? {
         // Read metadata, amending image and animation_url if values present
         let path = file?.path();
-        trace!(
-            "reading metadata from '{}'...",
-            path.to_str().expect(PATH_TO_STRING_MSG)
-        );
-        let file = fs::File::open(&path).with_context(|| {
-            format!(
-                "unable to read metadata from {}",
-                path.to_str().expect(PATH_TO_STRING_MSG)
-            )
-        })?;
-        let mut json: serde_json::Value = serde_json::from_reader(file).with_context(|| {
-            format!(
-                "unable to read metadata as JSON from {}",
-                path.to_str().expect(PATH_TO_STRING_MSG)
-            )
-        })?;
-
-        // Update url fields
-        let mut updated = update(&mut json, "image", base_uri)?;
-        updated |= update(&mut json, "animation_url", base_uri)?;
-
-        if updated {
-            let mut file = fs::File::create(&path)?;
-            write!(file, "{}", serde_json::to_string_pretty(&json)?)?;
-            trace!("updated '{}'", path.to_str().expect(PATH_TO_STRING_MSG));
-        } else {
+        if !is_metadata(&path) {
             trace!(
-                "no changes made to '{}'...",
+                "skipping '{}' as not metadata...",
                 path.to_str().expect(PATH_TO_STRING_MSG)
             );
+            continue;
+        }
+        deploy_file(&path, base_uri)?;
+    }
+
+    Ok(())
+}
+
+fn deploy_file(path: &Path, base_uri: &Url) -> Result<()> {
+    trace!(
+        "reading metadata from '{}'...",
+        path.to_str().expect(PATH_TO_STRING_MSG)
+    );
+    let file = fs::File::open(path).with_context(|| {
+        format!(
+            "unable to read metadata from {}",
+            path.to_str().expect(PATH_TO_STRING_MSG)
+        )
+    })?;
+    let mut json: serde_json::Value = serde_json::from_reader(file).with_context(|| {
+        format!(
+            "unable to read metadata as JSON from {}",
+            path.to_str().expect(PATH_TO_STRING_MSG)
+        )
+    })?;
+
+    // Update url fields, including those of each asset within combined cip25 metadata
+    let mut updated = false;
+    match json.get_mut(CIP25_POLICIES).and_then(|p| p.as_object_mut()) {
+        Some(policies) => {
+            for assets in policies.values_mut().filter_map(|a| a.as_object_mut()) {
+                for asset in assets.values_mut() {
+                    updated |= update_token(asset, base_uri, true)?;
+                }
+            }
         }
+        None => updated = update_token(&mut json, base_uri, false)?,
+    }
+
+    if updated {
+        let mut file = fs::File::create(path)?;
+        write!(file, "{}", serde_json::to_string_pretty(&json)?)?;
+        trace!("updated '{}'", path.to_str().expect(PATH_TO_STRING_MSG));
+    } else {
+        trace!(
+            "no changes made to '{}'...",
+            path.to_str().expect(PATH_TO_STRING_MSG)
+        );
     }
 
     Ok(())
 }
 
-fn update(json: &mut Value, field: &str, base_uri: &Url) -> Result<bool> {
-    if let Some(url) = json.get(field).and_then(|v| v.as_str()) {
-        let mut url = parse_url(url)?;
-        let file = url.path_segments().unwrap().last().unwrap();
+fn is_metadata(path: &Path) -> bool {
+    // Metadata is either named by token number, by ERC-1155 hex token id or uses a .json
+    // extension, allowing media to be output alongside metadata (e.g. Candy Machine assets)
+    if !path.is_file() {
+        return false;
+    }
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => extension.eq_ignore_ascii_case("json"),
+        None => path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|name| {
+                name.chars().all(|c| c.is_ascii_digit())
+                    || (name.len() == ERC1155_ID_LENGTH
+                        && name.chars().all(|c| c.is_ascii_hexdigit()))
+            }),
+    }
+}
+
+fn update_token(json: &mut Value, base_uri: &Url, chunked: bool) -> Result<bool> {
+    let mut updated = false;
+    for field in URL_FIELDS {
+        updated |= update(json, field, base_uri, chunked)?;
+    }
+    for (pointer, field) in URL_ARRAY_FIELDS {
+        if let Some(items) = json.pointer_mut(pointer).and_then(|f| f.as_array_mut()) {
+            for item in items {
+                updated |= update(item, field, base_uri, chunked)?;
+            }
+        }
+    }
+    Ok(updated)
+}
+
+fn update(json: &mut Value, field: &str, base_uri: &Url, chunked: bool) -> Result<bool> {
+    if let Some(url) = json.get(field).and_then(cip25::unchunk) {
+        let mut url = parse_url(&url)?;
+        let file = url.path_segments().unwrap().next_back().unwrap();
         url = base_uri.join(file)?;
-        json[field] = Value::String(url.to_string());
+        json[field] = if chunked {
+            cip25::chunk(url.as_str())
+        } else {
+            Value::String(url.to_string())
+        };
         trace!("updated url of '{field}' to '{url}'");
         return Ok(true);
     }
@@ -1,15 +1,28 @@
 use crate::config::Color;
-use anyhow::{Context, Result};
-use image::{DynamicImage, ImageBuffer};
+use anyhow::{anyhow, Context, Result};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageBuffer, Rgba};
 use log::trace;
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{Options, Tree};
 use rusttype::Font;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{ErrorKind, Read};
+use std::path::Path;
 use std::time::Duration;
+use symphonia::core::errors::Error;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::TimeBase;
 
 pub(crate) trait Cache<T> {
     fn get(&mut self, key: &str) -> Result<&T>;
+
+    /// Drops any cached copy of the key, so that it is read again on next use.
+    fn remove(&mut self, key: &str);
 }
 
 pub(crate) struct ImageCache(HashMap<String, DynamicImage>);
@@ -18,17 +31,36 @@ impl ImageCache {
     pub(crate) fn new() -> Self {
         Self(HashMap::new())
     }
+
+    pub(crate) fn resize(&mut self, key: &str, width: u32, height: u32) -> Result<&DynamicImage> {
+        let image_key = format!("{key} {width}x{height}");
+        if !self.0.contains_key(&image_key) {
+            trace!("caching '{image_key}' for next use...");
+            let image = self
+                .get(key)?
+                .resize_exact(width, height, FilterType::Triangle);
+            self.0.insert(image_key.clone(), image);
+        }
+        Ok(self.0.get(&image_key).expect("could not get cached image"))
+    }
 }
 
 impl Cache<DynamicImage> for ImageCache {
     fn get(&mut self, key: &str) -> Result<&DynamicImage> {
         if !self.0.contains_key(key) {
             trace!("caching '{key}' for next use...");
-            let image = image::open(&key).with_context(|| format!("unable to open {key}"))?;
+            let image = image::open(key).with_context(|| format!("unable to open {key}"))?;
             self.0.insert(key.to_string(), image);
         }
         Ok(self.0.get(key).expect("could not get cached image"))
     }
+
+    fn remove(&mut self, key: &str) {
+        // Resized images are keyed by the image along with their size
+        let prefix = format!("{key} ");
+        self.0
+            .retain(|image_key, _| image_key != key && !image_key.starts_with(&prefix));
+    }
 }
 
 pub(crate) struct AudioCache(HashMap<String, Duration>);
@@ -42,17 +74,68 @@ impl AudioCache {
 impl Cache<Duration> for AudioCache {
     fn get(&mut self, key: &str) -> Result<&Duration> {
         if !self.0.contains_key(key) {
-            let file = File::open(key.clone()).with_context(|| "error opening audio file")?;
-            let size = file
-                .metadata()
-                .with_context(|| format!("unable to retrieve metadata for '{key}'"))?
-                .len();
-            let reader = BufReader::new(file);
-            let reader = mp4::Mp4Reader::read_header(reader, size)?;
-            self.0.insert(key.to_string(), reader.duration());
+            trace!("caching duration of '{key}' for next use...");
+            let duration = audio_duration(key)
+                .with_context(|| format!("unable to determine the duration of '{key}'"))?;
+            self.0.insert(key.to_string(), duration);
         }
         Ok(self.0.get(key).expect("could not get cached audio"))
     }
+
+    fn remove(&mut self, key: &str) {
+        self.0.remove(key);
+    }
+}
+
+fn audio_duration(path: &str) -> Result<Duration> {
+    // Probe the container format, using the file extension as a hint
+    let file = File::open(path).with_context(|| "error opening audio file")?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = Path::new(path).extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .with_context(|| "unsupported audio format")?
+        .format;
+
+    let track = format
+        .default_track()
+        .ok_or_else(|| anyhow!("no audio track found"))?;
+    let track_id = track.id;
+    let time_base = match (track.codec_params.time_base, track.codec_params.sample_rate) {
+        (Some(time_base), _) => time_base,
+        (None, Some(sample_rate)) => TimeBase::new(1, sample_rate),
+        (None, None) => return Err(anyhow!("unable to determine the audio time base")),
+    };
+
+    // Use the frame count from the container when available, otherwise read through all packets
+    let frames = match track.codec_params.n_frames {
+        Some(frames) => frames,
+        None => {
+            let mut frames = 0;
+            loop {
+                match format.next_packet() {
+                    Ok(packet) if packet.track_id() == track_id => {
+                        frames = frames.max(packet.ts() + packet.dur())
+                    }
+                    Ok(_) => {}
+                    Err(Error::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+                    Err(e) => return Err(e).with_context(|| "unable to read audio packet"),
+                }
+            }
+            frames
+        }
+    };
+
+    let time = time_base.calc_time(frames);
+    Ok(Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac))
 }
 
 pub(crate) struct ColorCache(HashMap<String, DynamicImage>);
@@ -70,7 +153,7 @@ impl ColorCache {
         let key = format!("{} {width}x{height}", color.hex);
         if !self.0.contains_key(&key) {
             trace!("caching '{key}' for next use...");
-            let buffer = ImageBuffer::from_pixel(width, height, color.rgba.clone());
+            let buffer = ImageBuffer::from_pixel(width, height, color.rgba);
             let image = DynamicImage::ImageRgba8(buffer);
             self.0.insert(key.clone(), image);
         }
@@ -89,7 +172,7 @@ impl<'a> FontCache<'a> {
 impl<'a> Cache<Font<'a>> for FontCache<'a> {
     fn get(&mut self, key: &str) -> Result<&Font<'a>> {
         if !self.0.contains_key(key) {
-            let file = std::fs::File::open(&key).expect("could not open font file");
+            let file = std::fs::File::open(key).expect("could not open font file");
             let mut reader = std::io::BufReader::new(file);
             let mut buffer = Vec::new();
             reader.read_to_end(&mut buffer)?;
@@ -100,4 +183,132 @@ impl<'a> Cache<Font<'a>> for FontCache<'a> {
         }
         Ok(self.0.get(key).expect("could not get cached font"))
     }
+
+    fn remove(&mut self, key: &str) {
+        self.0.remove(key);
+    }
+}
+
+pub(crate) struct SvgCache {
+    options: Options<'static>,
+    // System fonts are only loaded once the first svg is parsed, as loading them is slow
+    fonts_loaded: bool,
+    trees: HashMap<String, Tree>,
+    images: HashMap<String, DynamicImage>,
+}
+
+impl SvgCache {
+    pub(crate) fn new() -> Self {
+        Self {
+            options: Options::default(),
+            fonts_loaded: false,
+            trees: HashMap::new(),
+            images: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn rasterize(
+        &mut self,
+        key: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<&DynamicImage> {
+        let image_key = format!("{key} {width}x{height}");
+        if !self.images.contains_key(&image_key) {
+            trace!("caching '{image_key}' for next use...");
+            let tree = self.get(key)?;
+            let mut pixmap = Pixmap::new(width, height)
+                .ok_or_else(|| anyhow!("unable to rasterize '{key}' at {width}x{height}"))?;
+            // Scale uniformly to fit, centring an svg of a different aspect ratio
+            let size = tree.size();
+            let scale = (width as f32 / size.width()).min(height as f32 / size.height());
+            resvg::render(
+                tree,
+                Transform::from_row(
+                    scale,
+                    0.0,
+                    0.0,
+                    scale,
+                    (width as f32 - size.width() * scale) / 2.0,
+                    (height as f32 - size.height() * scale) / 2.0,
+                ),
+                &mut pixmap.as_mut(),
+            );
+
+            // Convert from premultiplied alpha
+            let buffer = ImageBuffer::from_fn(width, height, |x, y| {
+                let pixel = pixmap
+                    .pixel(x, y)
+                    .expect("could not get rasterized pixel")
+                    .demultiply();
+                Rgba([pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()])
+            });
+            self.images
+                .insert(image_key.clone(), DynamicImage::ImageRgba8(buffer));
+        }
+        Ok(self
+            .images
+            .get(&image_key)
+            .expect("could not get cached image"))
+    }
+}
+
+impl Cache<Tree> for SvgCache {
+    fn get(&mut self, key: &str) -> Result<&Tree> {
+        if !self.trees.contains_key(key) {
+            trace!("caching '{key}' for next use...");
+            let data = std::fs::read(key).with_context(|| format!("unable to open {key}"))?;
+            if !self.fonts_loaded {
+                trace!("loading system fonts for svg text...");
+                self.options.fontdb_mut().load_system_fonts();
+                self.fonts_loaded = true;
+            }
+            // Resolve any relative paths within the svg against its own directory
+            self.options.resources_dir = Path::new(key).parent().map(|p| p.to_path_buf());
+            let tree = Tree::from_data(&data, &self.options)
+                .with_context(|| format!("unable to parse {key} as svg"))?;
+            self.trees.insert(key.to_string(), tree);
+        }
+        Ok(self.trees.get(key).expect("could not get cached svg"))
+    }
+
+    fn remove(&mut self, key: &str) {
+        // Rasterized images are keyed by the svg along with their size
+        let prefix = format!("{key} ");
+        self.trees.remove(key);
+        self.images
+            .retain(|image_key, _| !image_key.starts_with(&prefix));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    #[test]
+    fn rasterize_uniformly() {
+        // A wide svg is fitted to the width of a square image, centred vertically
+        let path = std::env::temp_dir().join(format!("ng-svg-{:08x}.svg", rand::random::<u32>()));
+        std::fs::write(
+            &path,
+            r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 64 32"><rect width="64" height="32" fill="#ff0000"/></svg>"##,
+        )
+        .unwrap();
+        let mut cache = SvgCache::new();
+        let image = cache
+            .rasterize(path.to_str().unwrap(), 64, 64)
+            .unwrap()
+            .clone();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(image.dimensions(), (64, 64));
+        let red = Rgba([255, 0, 0, 255]);
+        for (x, y) in [(0, 16), (63, 16), (32, 32), (0, 47), (63, 47)] {
+            assert_eq!(image.get_pixel(x, y), red, "pixel {x},{y}");
+        }
+        for (x, y) in [(32, 0), (32, 14), (32, 49), (32, 63)] {
+            assert_eq!(image.get_pixel(x, y)[3], 0, "pixel {x},{y}");
+        }
+    }
 }
@@ -1,39 +1,271 @@
 mod caches;
+pub(crate) mod plan;
+mod svg;
 
 use self::caches::Cache;
-use crate::config::{Attribute, AttributeOption, Color};
-use crate::generation::caches::{AudioCache, ColorCache, FontCache, ImageCache};
-use crate::random::AttributeValue;
-use crate::{metadata, Config, PATH_TO_STRING_MSG};
-use anyhow::{Context, Result};
+use crate::config::{
+    Attribute, AttributeOption, Cip25, Collection, Color, CustomMetadata, DisplayType, Edition,
+    EditionOutput, Erc1155, Hidden, MediaFormat, Metaplex, Part, Standard, Tzip21, Video,
+};
+use crate::generation::caches::{AudioCache, ColorCache, FontCache, ImageCache, SvgCache};
+use crate::provenance::{self, Provenance};
+use crate::random::{AttributeValue, Token};
+use crate::watch::{self, Change, Watcher};
+use crate::{metadata, output, Config, PATH_TO_STRING_MSG};
+use anyhow::{anyhow, Context, Result};
 use ffmpeg_cli::{FfmpegBuilder, Parameter};
 use hhmmss::Hhmmss;
-use image::{imageops, DynamicImage};
+use image::{imageops, DynamicImage, ImageFormat, ImageOutputFormat};
 use imageproc::drawing::{draw_text, text_size};
-use log::{debug, error, info, trace};
+use indexmap::IndexMap;
+use log::{debug, error, info, trace, warn};
 use rusttype::Scale;
+use serde::Serialize;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fs::File;
 use std::fs::OpenOptions;
+use std::io::{BufReader, Cursor};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::str::FromStr;
 use std::time::{Duration, Instant};
 
 const ID: &str = "id";
+const DNA: &str = "dna";
+const EDITION: &str = "edition";
+// The file names (without extension) of the collection media copied into the media folder
+const COLLECTION_IMAGE_NAME: &str = "collection";
+const COLLECTION_BANNER_NAME: &str = "banner";
+// The file name (without extension) of the placeholder image copied into the media folder
+const HIDDEN_IMAGE_NAME: &str = "hidden";
+// The separator between distinct values of attributes merged into a single trait
+const MERGED_VALUE_SEPARATOR: &str = ", ";
+// The number of trailing lines of ffmpeg output included when reporting a failed encode
+const FFMPEG_STDERR_LINES: usize = 20;
+// The allowed difference between the expected and actual duration of a generated video
+const VIDEO_DURATION_TOLERANCE: Duration = Duration::from_secs(1);
+// The containers whose duration can be verified once generated
+const MP4_CONTAINERS: [&str; 3] = ["m4v", "mov", "mp4"];
+// The ffmpeg options (and alias) of the video filter chain
+const VIDEO_FILTER_ARGS: [&str; 2] = ["vf", "filter:v"];
 
+/// How a token is handled when its media could not be generated.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum FailurePolicy {
+    /// Stop generation at the first failed token.
+    Abort,
+    /// Log the failed token, remove any partial output and continue with the next token.
+    Skip,
+}
+
+impl FromStr for FailurePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "abort" => Ok(FailurePolicy::Abort),
+            "skip" => Ok(FailurePolicy::Skip),
+            _ => Err(anyhow!("unknown failure policy '{s}'")),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn generate(
-    source: &PathBuf,
+    source: &Path,
     output: &str,
     media: &str,
     metadata: &str,
-    config: Config,
+    config: &Config,
+    previous: Option<&Config>,
+    retries: u32,
+    on_failure: FailurePolicy,
+    watcher: Option<&Watcher>,
 ) -> Result<()> {
     // Validate the config before starting generation
-    validate(&config)?;
+    validate(config)?;
+
+    // Initialise generator and start, resuming from the existing plan once the config changes
+    let mut generator =
+        Generator::new(source, output, media, metadata, config, retries, on_failure);
+    let resumed = match previous {
+        Some(previous) => generator.resume(config, previous).await?,
+        None => None,
+    };
+    let mut batches = match resumed {
+        Some(batches) => batches,
+        None => {
+            output::init(source, output, media, metadata)?;
+            generator.start(config).await?
+        }
+    };
 
-    // Initialise generator and start
-    Generator::new(source, output, media, metadata, &config)
-        .start(&config)
-        .await
+    // Re-render the tokens affected by changed files, until the configuration changes
+    let Some(watcher) = watcher else {
+        return Ok(());
+    };
+    while let Change::Files(changed) = watcher.wait()? {
+        generator.update(config, &mut batches, &changed).await?;
+    }
+    Ok(())
+}
+
+/// A token rendered without video, for previewing the collection.
+pub(crate) struct Sample {
+    pub(crate) token: usize,
+    pub(crate) image: PathBuf,
+    /// The trait type and value of each attribute included within metadata.
+    pub(crate) attributes: Vec<(String, String)>,
+}
+
+/// Renders randomly generated tokens (without video) for previewing the collection, retaining
+/// cached media to re-render them as files change.
+pub(crate) struct Sampler<'a> {
+    generator: Generator<'a>,
+    config: &'a Config,
+    tokens: Vec<Token<'a>>,
+}
+
+impl<'a> Sampler<'a> {
+    /// Samples the configured supply of tokens, using the configured weights and conditions, to be
+    /// rendered at a reduced resolution fitting within the size.
+    pub(crate) fn new(
+        source: &Path,
+        output: &str,
+        media: &str,
+        metadata: &str,
+        config: &'a Config,
+        size: u32,
+    ) -> Result<Self> {
+        let tokens = crate::random::generate(config, None)
+            .with_context(|| "failed to sample the collection")?;
+        let mut generator = Generator::new(
+            source,
+            output,
+            media,
+            metadata,
+            config,
+            0,
+            FailurePolicy::Abort,
+        );
+        generator.render_video = false;
+        generator.size = Some(size);
+        Ok(Self {
+            generator,
+            config,
+            tokens,
+        })
+    }
+
+    /// Renders every sampled token within the output directory.
+    pub(crate) async fn render(&mut self) -> Result<Vec<Sample>> {
+        let mut samples = Vec::with_capacity(self.tokens.len());
+        for (i, attributes) in self.tokens.iter().enumerate() {
+            let token = i + self.config.start_token;
+            let image = self
+                .generator
+                .render_token(token, attributes)
+                .await
+                .with_context(|| format!("failed to render token {token}"))?;
+            samples.extend(image.map(|image| Sample::new(token, image, attributes)));
+        }
+        Ok(samples)
+    }
+
+    /// Re-renders the sampled tokens affected by changed files, returning those rendered.
+    pub(crate) async fn update(&mut self, changed: &[PathBuf]) -> Vec<Sample> {
+        let files = self.generator.refresh(self.config, changed);
+        let mut samples = Vec::new();
+        for (i, attributes) in self.tokens.iter().enumerate() {
+            if !affected(attributes, &files) {
+                continue;
+            }
+            let token = i + self.config.start_token;
+            match self.generator.render_token(token, attributes).await {
+                Ok(image) => {
+                    samples.extend(image.map(|image| Sample::new(token, image, attributes)))
+                }
+                Err(e) => error!("failed to render token {token}: {e:#}"),
+            }
+        }
+        samples
+    }
+}
+
+impl Sample {
+    fn new(token: usize, image: PathBuf, attributes: &Token) -> Self {
+        Self {
+            token,
+            image,
+            attributes: attributes
+                .iter()
+                .filter(|(attribute, _, _)| attribute.metadata)
+                .map(|(attribute, value, _)| {
+                    (attribute.trait_type().to_string(), value.to_string())
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Renders the tokens (without video) within the output directory, numbered from the start token,
+/// at a reduced resolution fitting within the size, returning the image of each token (if any).
+pub(crate) async fn render(
+    source: &Path,
+    output: &str,
+    media: &str,
+    metadata: &str,
+    config: &Config,
+    tokens: &[Token<'_>],
+    size: u32,
+) -> Result<Vec<Option<PathBuf>>> {
+    let mut generator = Generator::new(
+        source,
+        output,
+        media,
+        metadata,
+        config,
+        0,
+        FailurePolicy::Abort,
+    );
+    generator.render_video = false;
+    generator.size = Some(size);
+    let mut images = Vec::with_capacity(tokens.len());
+    for (i, attributes) in tokens.iter().enumerate() {
+        let token = i + config.start_token;
+        images.push(
+            generator
+                .render_token(token, attributes)
+                .await
+                .with_context(|| format!("failed to render token {token}"))?,
+        );
+    }
+    Ok(images)
+}
+
+/// The configured files within the source directory, used by options or copied into the output.
+fn files(config: &Config) -> Vec<&PathBuf> {
+    config
+        .attributes
+        .iter()
+        .flat_map(|attribute| attribute.options.values())
+        .flat_map(|option| option.paths())
+        .chain(config.hidden.iter().map(|hidden| &hidden.image))
+        .chain(
+            config
+                .collection
+                .iter()
+                .flat_map(|collection| collection.image.iter().chain(collection.banner.iter())),
+        )
+        .collect()
+}
+
+/// Whether any option of the token uses one of the files.
+fn affected(token: &Token, files: &[&PathBuf]) -> bool {
+    token
+        .iter()
+        .any(|(_, _, option)| option.paths().iter().any(|path| files.contains(path)))
 }
 
 pub(crate) fn validate(config: &Config) -> Result<()> {
@@ -71,105 +303,626 @@ struct Generator<'a> {
     description: &'a str,
     external_url: Option<&'a String>,
     background_color: Option<&'a Color>,
-    start_token: usize,
+    edition: Option<&'a str>,
+    media_format: MediaFormat,
+    standard: Standard,
+    metaplex: Option<&'a Metaplex>,
+    erc1155: &'a Erc1155,
+    tzip21: &'a Tzip21,
+    cip25: Option<&'a Cip25>,
+    cip25_assets: IndexMap<String, serde_json::Value>,
+    collection: Option<&'a Collection>,
+    hidden: Option<&'a Hidden>,
+    custom_metadata: &'a CustomMetadata,
+    video: &'a Video,
+    render_video: bool,
+    // The maximum width and height of token images, reducing their resolution (e.g. to preview)
+    size: Option<u32>,
+    // The scale of the layers of the current token, as determined by its first layer
+    scale: f32,
+    retries: u32,
+    on_failure: FailurePolicy,
     caches: Caches<'a>,
 }
 
+/// The numbered tokens of an output directory, with the edition of each (if any).
+type Tokens<'a> = Vec<(usize, Token<'a>, Option<&'a Edition>)>;
+
+/// The tokens generated within an output directory, retained to re-render them as files change.
+struct Batch<'a> {
+    output: PathBuf,
+    media: PathBuf,
+    metadata: PathBuf,
+    tokens: Tokens<'a>,
+    skipped: Vec<usize>,
+    cip25_assets: IndexMap<String, serde_json::Value>,
+}
+
+/// A layer of a token: either an attribute option or a part of a multi-part option.
+enum Layer<'a> {
+    Option(&'a AttributeOption),
+    Part(&'a Part),
+}
+
 struct Caches<'a> {
     audio: AudioCache,
     color: ColorCache,
     font: FontCache<'a>,
     image: ImageCache,
+    svg: SvgCache,
+}
+
+impl Caches<'_> {
+    /// Drops any cached copy of the file, so that it is read again on next use.
+    fn remove(&mut self, key: &str) {
+        self.audio.remove(key);
+        self.font.remove(key);
+        self.image.remove(key);
+        self.svg.remove(key);
+    }
 }
 
 impl<'a> Generator<'a> {
     fn new(
-        source: &PathBuf,
+        source: &Path,
         output: &str,
         media: &str,
         metadata: &str,
         config: &'a Config,
+        retries: u32,
+        on_failure: FailurePolicy,
     ) -> Self {
         let media = source.join(output).join(media);
         let metadata = source.join(output).join(metadata);
         Self {
-            source: source.clone(),
+            source: source.to_path_buf(),
             media,
             metadata,
             name: config.name.as_ref(),
             description: config.description.as_ref(),
             external_url: config.external_url.as_ref(),
             background_color: config.background_color.as_ref(),
-            start_token: config.start_token,
+            edition: None,
+            media_format: config.media_format,
+            standard: config.standard,
+            metaplex: config.metaplex.as_ref(),
+            erc1155: &config.erc1155,
+            tzip21: &config.tzip21,
+            cip25: config.cip25.as_ref(),
+            cip25_assets: IndexMap::new(),
+            collection: config.collection.as_ref(),
+            hidden: config.hidden.as_ref(),
+            custom_metadata: &config.metadata,
+            video: &config.video,
+            render_video: true,
+            size: None,
+            scale: 1.0,
+            retries,
+            on_failure,
             caches: Caches {
                 audio: AudioCache::new(),
                 color: ColorCache::new(),
                 font: FontCache::new(),
                 image: ImageCache::new(),
+                svg: SvgCache::new(),
             },
         }
     }
 
-    async fn start(&mut self, config: &Config) -> Result<()> {
+    async fn start(&mut self, config: &'a Config) -> Result<Vec<Batch<'a>>> {
         // Generate the collection based on configuration
         info!("starting nifty generation...");
         let current = Instant::now();
-        for (i, attributes) in crate::random::generate(&config)
-            .with_context(|| "failed to generate the collection")?
+        let output = self
+            .metadata
+            .parent()
+            .expect("could not get output folder")
+            .to_path_buf();
+        let batches = match config.edition_output {
+            _ if config.editions.is_empty() => {
+                vec![self.generate_editions(config, &output, &[None]).await?]
+            }
+            EditionOutput::Continuous => {
+                let editions: Vec<Option<&Edition>> = config.editions.iter().map(Some).collect();
+                vec![self.generate_editions(config, &output, &editions).await?]
+            }
+            EditionOutput::Separate => {
+                // Each edition is output within its own directory, reusing the shared caches
+                let media = self.media.file_name().expect("could not get media folder");
+                let metadata = self
+                    .metadata
+                    .file_name()
+                    .expect("could not get metadata folder");
+                let (media, metadata) = (
+                    media.to_str().expect(PATH_TO_STRING_MSG).to_string(),
+                    metadata.to_str().expect(PATH_TO_STRING_MSG).to_string(),
+                );
+                // The top-level media and metadata directories are replaced by those of each edition
+                for directory in [&self.media, &self.metadata] {
+                    std::fs::remove_dir(directory).with_context(|| {
+                        format!(
+                            "could not remove output directory {}",
+                            directory.to_str().expect(PATH_TO_STRING_MSG)
+                        )
+                    })?;
+                }
+                let mut batches = Vec::with_capacity(config.editions.len());
+                for edition in &config.editions {
+                    info!("generating edition '{}'...", edition.name);
+                    let edition_output = output::init(&output, &edition.name, &media, &metadata)?;
+                    self.media = edition_output.join(&media);
+                    self.metadata = edition_output.join(&metadata);
+                    batches.push(
+                        self.generate_editions(config, &edition_output, &[Some(edition)])
+                            .await?,
+                    );
+                }
+                batches
+            }
+        };
+
+        info!("generation completed in {}", current.elapsed().hhmmssxxx());
+        let skipped: Vec<usize> = batches
             .iter()
-            .enumerate()
-        {
-            self.generate_token(i + self.start_token, attributes)
-                .await?;
+            .flat_map(|batch| batch.skipped.iter().copied())
+            .collect();
+        if !skipped.is_empty() {
+            warn!(
+                "{} token(s) could not be generated and were skipped: {}",
+                skipped.len(),
+                skipped
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+        }
+        Ok(batches)
+    }
+
+    /// Generates the tokens of one or more editions (or the whole collection) within the output
+    /// directory.
+    async fn generate_editions(
+        &mut self,
+        config: &'a Config,
+        output: &Path,
+        editions: &[Option<&'a Edition>],
+    ) -> Result<Batch<'a>> {
+        let mut tokens = Vec::with_capacity(config.supply);
+        for &edition in editions {
+            let start_token = edition.map_or(config.start_token, |e| e.tokens().start);
+            let generated =
+                crate::random::generate(config, edition).with_context(|| match edition {
+                    Some(edition) => format!("failed to generate the '{}' edition", edition.name),
+                    None => "failed to generate the collection".to_string(),
+                })?;
+            tokens.extend(
+                generated
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, token)| (i + start_token, token, edition)),
+            );
+        }
+        plan::save(output, &tokens).with_context(|| "unable to save the generation plan")?;
+
+        let mut skipped = Vec::new();
+        for (token, attributes, edition) in &tokens {
+            let token = *token;
+            self.set_edition(config, *edition);
+            match self.generate_token(token, attributes).await {
+                Ok(()) => {}
+                Err(e) => match self.on_failure {
+                    FailurePolicy::Abort => {
+                        return Err(e).with_context(|| format!("failed to generate token {token}"))
+                    }
+                    FailurePolicy::Skip => {
+                        error!("skipping token {token}: {e:#}");
+                        self.remove_media(token);
+                        skipped.push(token);
+                    }
+                },
+            }
+        }
+
+        let mut batch = Batch {
+            output: output.to_path_buf(),
+            media: self.media.clone(),
+            metadata: self.metadata.clone(),
+            tokens,
+            skipped,
+            cip25_assets: IndexMap::new(),
+        };
+        self.finish(config, &batch)?;
+        batch.cip25_assets = std::mem::take(&mut self.cip25_assets);
+        Ok(batch)
+    }
+
+    /// Resumes generation from the plan within the output directory once the configuration
+    /// changes, re-rendering only the tokens whose configured options changed (or whose media is
+    /// missing). Returns `None` if the plan no longer matches the configuration, requiring the
+    /// collection to be regenerated.
+    async fn resume(
+        &mut self,
+        config: &'a Config,
+        previous: &Config,
+    ) -> Result<Option<Vec<Batch<'a>>>> {
+        let planned = match self.planned(config) {
+            Ok(planned) => planned,
+            Err(e) => {
+                warn!("the generation plan no longer matches the configuration ({e:#}) - the collection needs to be regenerated");
+                return Ok(None);
+            }
+        };
+
+        info!("resuming nifty generation from the generation plan...");
+        let current = Instant::now();
+        let settings_changed = config.settings_changed(previous);
+        let (media, metadata) = (
+            self.media
+                .file_name()
+                .expect("could not get media folder")
+                .to_owned(),
+            self.metadata
+                .file_name()
+                .expect("could not get metadata folder")
+                .to_owned(),
+        );
+        let mut batches = Vec::with_capacity(planned.len());
+        let (mut updated, mut failed) = (0, 0);
+        for (output, tokens) in planned {
+            self.media = output.join(&media);
+            self.metadata = output.join(&metadata);
+            if self.standard == Standard::Cip25 {
+                self.cip25_assets = self
+                    .load_cip25_assets()
+                    .with_context(|| "unable to read combined cip25 metadata")?;
+            }
+            let mut skipped = Vec::new();
+            for (token, attributes, edition) in &tokens {
+                let token = *token;
+                if !settings_changed
+                    && !attributes.iter().any(|(attribute, value, _)| {
+                        config.option_changed(previous, &attribute.name, value.name)
+                    })
+                    && output::token_image(&self.media, token).is_some()
+                {
+                    continue;
+                }
+                self.set_edition(config, *edition);
+                match self.generate_token(token, attributes).await {
+                    Ok(()) => updated += 1,
+                    Err(e) => match self.on_failure {
+                        FailurePolicy::Abort => {
+                            return Err(e)
+                                .with_context(|| format!("failed to generate token {token}"))
+                        }
+                        FailurePolicy::Skip => {
+                            error!("skipping token {token}: {e:#}");
+                            self.remove_media(token);
+                            skipped.push(token);
+                        }
+                    },
+                }
+            }
+            failed += skipped.len();
+
+            let mut batch = Batch {
+                output,
+                media: self.media.clone(),
+                metadata: self.metadata.clone(),
+                tokens,
+                skipped,
+                cip25_assets: IndexMap::new(),
+            };
+            self.finish(config, &batch)?;
+            batch.cip25_assets = std::mem::take(&mut self.cip25_assets);
+            batches.push(batch);
+        }
+        info!(
+            "{updated} token(s) re-rendered in {}",
+            current.elapsed().hhmmssxxx()
+        );
+        if failed > 0 {
+            warn!("{failed} token(s) could not be re-rendered and were skipped");
+        }
+        Ok(Some(batches))
+    }
+
+    /// Resolves the planned tokens of each output directory against the configuration, failing if
+    /// the plan is missing or no longer matches the configured tokens, attributes or editions.
+    fn planned(&self, config: &'a Config) -> Result<Vec<(PathBuf, Tokens<'a>)>> {
+        let output = self
+            .metadata
+            .parent()
+            .expect("could not get output folder")
+            .to_path_buf();
+        // Separately output editions are each planned within their own directory
+        let batches: Vec<(PathBuf, Vec<Option<&Edition>>)> = match config.edition_output {
+            _ if config.editions.is_empty() => vec![(output, vec![None])],
+            EditionOutput::Continuous => {
+                vec![(output, config.editions.iter().map(Some).collect())]
+            }
+            EditionOutput::Separate => config
+                .editions
+                .iter()
+                .map(|edition| (output.join(&edition.name), vec![Some(edition)]))
+                .collect(),
+        };
+
+        let mut planned = Vec::with_capacity(batches.len());
+        for (output, editions) in batches {
+            if !plan::exists(&output) {
+                return Err(anyhow!(
+                    "no generation plan was found within '{}'",
+                    output.to_str().expect(PATH_TO_STRING_MSG)
+                ));
+            }
+            let plan = plan::load(&output)?;
+            let expected = editions.iter().flat_map(|edition| {
+                edition
+                    .map_or(
+                        config.start_token..config.start_token + config.supply,
+                        |e| e.tokens(),
+                    )
+                    .map(move |token| (token, edition.map(|e| e.name.as_str())))
+            });
+            if !plan
+                .iter()
+                .map(|planned| (planned.token, planned.edition.as_deref()))
+                .eq(expected)
+            {
+                return Err(anyhow!("the configured tokens or editions changed"));
+            }
+            planned.push((output, plan::tokens(config, &plan)?));
+        }
+        Ok(planned)
+    }
+
+    /// Names subsequent tokens as those of the edition, if any.
+    fn set_edition(&mut self, config: &'a Config, edition: Option<&'a Edition>) {
+        self.name = edition
+            .and_then(|e| e.token_name.as_deref())
+            .unwrap_or(&config.name);
+        self.edition = edition.map(|e| e.name.as_str());
+    }
+
+    /// Saves the records covering every token of the batch: the combined cip25 metadata,
+    /// provenance, hidden and collection metadata.
+    fn finish(&mut self, config: &Config, batch: &Batch) -> Result<()> {
+        let generated: Vec<usize> = batch
+            .tokens
+            .iter()
+            .map(|(token, _, _)| *token)
+            .filter(|token| !batch.skipped.contains(token))
+            .collect();
+
+        if self.standard == Standard::Cip25 {
+            self.save_cip25_metadata()
+                .with_context(|| "unable to save combined cip25 metadata")?;
+        }
+
+        Provenance::new(
+            &self.media,
+            &generated,
+            batch.tokens.first().map_or(config.start_token, |t| t.0),
+            batch.tokens.len(),
+            &self.video.container,
+        )
+        .and_then(|provenance| provenance.save(&batch.output))
+        .with_context(|| "unable to save provenance record")?;
+
+        if let Some(hidden) = self.hidden {
+            self.save_hidden_metadata(hidden, &generated)
+                .with_context(|| "unable to save hidden metadata")?;
+        }
+
+        if let Some(collection) = self.collection {
+            self.save_collection_metadata(collection)
+                .with_context(|| "unable to save collection metadata")?;
         }
 
-        info!("generation completed in {}", current.elapsed().hhmmssxxx());
         Ok(())
     }
 
-    async fn generate_token(
+    /// Re-renders the generated tokens affected by changed files, then saves the records of each
+    /// affected batch. Tokens which fail are skipped, allowing the files to be fixed whilst
+    /// watching.
+    async fn update(
         &mut self,
-        token: usize,
-        attributes: &Vec<(&Attribute, &AttributeValue, &AttributeOption)>,
+        config: &'a Config,
+        batches: &mut [Batch<'a>],
+        changed: &[PathBuf],
     ) -> Result<()> {
+        let files = self.refresh(config, changed);
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        // Files copied into the output affect every batch
+        let copied = files.iter().any(|file| {
+            self.hidden.is_some_and(|hidden| &&hidden.image == file)
+                || self.collection.is_some_and(|collection| {
+                    collection.image.as_ref() == Some(*file)
+                        || collection.banner.as_ref() == Some(*file)
+                })
+        });
+        let current = Instant::now();
+        let mut updated = 0;
+        for batch in batches.iter_mut() {
+            let affected: Vec<usize> = (0..batch.tokens.len())
+                .filter(|&i| affected(&batch.tokens[i].1, &files))
+                .collect();
+            if affected.is_empty() && !copied {
+                continue;
+            }
+
+            self.media = batch.media.clone();
+            self.metadata = batch.metadata.clone();
+            self.cip25_assets = std::mem::take(&mut batch.cip25_assets);
+            for i in affected {
+                let (token, attributes, edition) = &batch.tokens[i];
+                let token = *token;
+                self.set_edition(config, *edition);
+                match self.generate_token(token, attributes).await {
+                    Ok(()) => batch.skipped.retain(|&t| t != token),
+                    Err(e) => {
+                        error!("skipping token {token}: {e:#}");
+                        self.remove_media(token);
+                        if !batch.skipped.contains(&token) {
+                            batch.skipped.push(token);
+                        }
+                    }
+                }
+                updated += 1;
+            }
+            let finished = self.finish(config, batch);
+            batch.cip25_assets = std::mem::take(&mut self.cip25_assets);
+            finished?;
+        }
+        info!(
+            "{updated} token(s) re-rendered in {}",
+            current.elapsed().hhmmssxxx()
+        );
+        Ok(())
+    }
+
+    /// Drops any cached copies of changed files, returning the configured files which changed.
+    fn refresh(&mut self, config: &'a Config, changed: &[PathBuf]) -> Vec<&'a PathBuf> {
+        let files: Vec<&PathBuf> = files(config)
+            .into_iter()
+            .filter(|file| changed.contains(&watch::resolve(&self.source, file)))
+            .collect();
+        for file in &files {
+            let path = self
+                .source
+                .join(file)
+                .into_os_string()
+                .into_string()
+                .expect(PATH_TO_STRING_MSG);
+            debug!("refreshing '{path}'...");
+            self.caches.remove(&path);
+        }
+        if files.is_empty() {
+            info!("no configured files changed");
+        }
+        files
+    }
+
+    /// Renders a token, returning its image (if any).
+    async fn render_token(
+        &mut self,
+        token: usize,
+        attributes: &Token<'_>,
+    ) -> Result<Option<PathBuf>> {
+        self.generate_token(token, attributes).await?;
+        Ok(
+            provenance::media_files(&self.media, token, &self.video.container)
+                .into_iter()
+                .next(),
+        )
+    }
+
+    async fn generate_token(&mut self, token: usize, attributes: &Token<'_>) -> Result<()> {
         info!("generating nifty #{}", token);
+        self.scale = 1.0;
 
         // Create a new image
-        let mut token_attributes = Vec::new();
+        let mut token_attributes: Vec<(metadata::Attribute, bool)> = Vec::new();
         let mut token_audio: Option<PathBuf> = None;
         let mut token_color: Option<&Color> = None;
         let mut token_image: Option<DynamicImage> = None;
+        let mut token_document = match self.media_format {
+            MediaFormat::Png => None,
+            MediaFormat::Svg => Some(svg::Document::new()),
+        };
 
-        // Process layers
+        // Add attributes to resulting metadata (if applicable)
+        for (attribute, value, option) in attributes {
+            if attribute.metadata {
+                if let Some(metadata_attribute) = metadata_attribute(attribute, *value, option) {
+                    merge_attribute(
+                        &mut token_attributes,
+                        metadata_attribute,
+                        matches!(option, AttributeOption::None { .. }),
+                    );
+                }
+            }
+
+            // Add audio duration to resulting metadata (if applicable)
+            if let (AttributeOption::Audio { file, .. }, Some(trait_type)) =
+                (option, &attribute.duration)
+            {
+                let duration = self.audio_duration(file)?;
+                token_attributes.push((
+                    metadata::Attribute::Number {
+                        trait_type,
+                        value: duration.as_secs_f64().round() as usize,
+                        max_value: None,
+                    },
+                    false,
+                ));
+            }
+        }
+
+        // Order layers by z-index, with the parts of multi-part options placed above the attribute
+        // at the same z-index
+        let mut layers: Vec<(usize, &Attribute, AttributeValue, Layer)> = Vec::new();
         for (layer, (attribute, value, option)) in attributes.iter().enumerate() {
+            let layer = attribute.z.unwrap_or(layer);
+            match option {
+                AttributeOption::Parts { parts, .. } => layers.extend(parts.iter().map(|part| {
+                    (
+                        part.z.unwrap_or(layer),
+                        *attribute,
+                        *value,
+                        Layer::Part(part),
+                    )
+                })),
+                _ => layers.push((layer, attribute, *value, Layer::Option(option))),
+            }
+        }
+        layers.sort_by_key(|(z, _, _, layer)| (*z, matches!(layer, Layer::Part(_))));
+
+        // Process layers
+        for (z, attribute, value, layer) in layers {
             debug!(
-                "processing attribute '{}' with value of '{value}' as layer {layer}",
+                "processing attribute '{}' with value of '{value}' as layer {z}",
                 attribute.name
             );
 
-            // Add attribute to resulting metadata (if applicable)
-            if attribute.metadata {
-                token_attributes.push(metadata::Attribute::String {
-                    trait_type: &attribute.name,
-                    value,
-                });
-            }
-
+            let option = match layer {
+                Layer::Option(option) => option,
+                Layer::Part(part) => {
+                    self.generate_file_layer(
+                        &mut token_document,
+                        &mut token_image,
+                        token_color,
+                        &part.file,
+                        part.vector(),
+                    )?;
+                    continue;
+                }
+            };
             match option {
                 AttributeOption::Audio { file, .. } => {
                     // Save audio until the end of token generation
                     token_audio = Some(file.clone());
-                    continue;
                 }
                 AttributeOption::Color { color, .. } => {
                     // Store color for later use (i.e. first image layer to determine width/height)
                     if token_color.is_none() {
-                        token_color = Some(&color);
+                        token_color = Some(color);
                     }
                 }
-                AttributeOption::Image { file, .. } => {
-                    token_image =
-                        Some(self.generate_image_layer(file, token_image, token_color)?);
+                AttributeOption::Image { file, .. } | AttributeOption::Svg { file, .. } => {
+                    let vector = matches!(option, AttributeOption::Svg { .. });
+                    self.generate_file_layer(
+                        &mut token_document,
+                        &mut token_image,
+                        token_color,
+                        file,
+                        vector,
+                    )?;
                 }
                 AttributeOption::Text {
                     font,
@@ -180,28 +933,44 @@ impl<'a> Generator<'a> {
                     color,
                     ..
                 } => {
-                    token_image = Some(self.generate_text(
-                        token,
-                        &mut token_image,
-                        font,
-                        &text,
-                        height,
-                        x,
-                        y,
-                        color,
-                    )?);
+                    // Text is written onto a transparent layer when composing a document
+                    let mut canvas = match &token_document {
+                        Some(document) => document
+                            .size()
+                            .map(|(width, height)| DynamicImage::new_rgba8(width, height)),
+                        None => token_image.take(),
+                    };
+                    let image =
+                        self.generate_text(token, &mut canvas, font, &text, height, x, y, color)?;
+                    match &mut token_document {
+                        Some(document) => {
+                            let mut data = Vec::new();
+                            image
+                                .write_to(&mut Cursor::new(&mut data), ImageOutputFormat::Png)
+                                .with_context(|| "unable to encode text layer")?;
+                            document.add_image(&data, "image/png", image.width(), image.height());
+                        }
+                        None => token_image = Some(image),
+                    }
                 }
-                AttributeOption::None { .. } => {}
+                AttributeOption::Number { .. }
+                | AttributeOption::Range { .. }
+                | AttributeOption::Parts { .. }
+                | AttributeOption::None { .. } => {}
             }
         }
 
         // Save token to output folder
-        if let Some(token_image) = token_image {
-            // Save image
-            let image_path = self.save_image(token, token_image)?;
-
+        let image_path = match (token_document, token_image) {
+            (Some(document), _) if document.size().is_some() => {
+                Some(self.save_document(token, &document, token_color)?)
+            }
+            (None, Some(token_image)) => Some(self.save_image(token, token_image)?),
+            _ => None,
+        };
+        if let Some(image_path) = image_path {
             // Check if video to be generated
-            let video_path = if let Some(audio) = token_audio {
+            let video_path = if let Some(audio) = token_audio.filter(|_| self.render_video) {
                 Some(self.generate_video(&image_path, &audio).await?)
             } else {
                 None
@@ -212,18 +981,44 @@ impl<'a> Generator<'a> {
                 .background_color
                 .as_ref()
                 .map(|color| color.hex.as_str()));
-            self.save_metadata(token, token_attributes, token_color, image_path, video_path)
-                .with_context(|| "unable to save token metadata")?;
+            self.save_metadata(
+                token,
+                &crate::random::dna(attributes),
+                token_attributes.into_iter().map(|(a, _)| a).collect(),
+                token_color,
+                image_path,
+                video_path,
+            )
+            .with_context(|| "unable to save token metadata")?;
         }
 
         Ok(())
     }
 
+    /// Adds an image (or svg) file as a layer of either the token document or token image.
+    fn generate_file_layer(
+        &mut self,
+        document: &mut Option<svg::Document>,
+        image: &mut Option<DynamicImage>,
+        color: Option<&Color>,
+        file: &Path,
+        vector: bool,
+    ) -> Result<()> {
+        match document {
+            Some(document) => self.generate_document_layer(document, file, vector),
+            None => {
+                *image = Some(self.generate_image_layer(file, image.take(), color, vector)?);
+                Ok(())
+            }
+        }
+    }
+
     fn generate_image_layer(
         &mut self,
-        file: &PathBuf,
+        file: &Path,
         mut token_image: Option<DynamicImage>,
         token_color: Option<&Color>,
+        vector: bool,
     ) -> Result<DynamicImage> {
         // Get image and cache for subsequent use
         let path = self
@@ -232,7 +1027,31 @@ impl<'a> Generator<'a> {
             .into_os_string()
             .into_string()
             .expect(PATH_TO_STRING_MSG);
-        let layer_image = self.caches.image.get(&path)?;
+        let layer_image = if vector {
+            // Rasterize at the resolution of the image, or at its own size when the first layer
+            let (width, height) = match &token_image {
+                Some(image) => (image.width(), image.height()),
+                None => {
+                    let size = self.caches.svg.get(&path)?.size();
+                    self.set_scale(size.width(), size.height());
+                    self.scaled(size.width(), size.height())
+                }
+            };
+            self.caches.svg.rasterize(&path, width, height)?
+        } else {
+            let image = self.caches.image.get(&path)?;
+            let (width, height) = (image.width() as f32, image.height() as f32);
+            if token_image.is_none() {
+                self.set_scale(width, height);
+            }
+            match self.scale < 1.0 {
+                true => {
+                    let (width, height) = self.scaled(width, height);
+                    self.caches.image.resize(&path, width, height)?
+                }
+                false => self.caches.image.get(&path)?,
+            }
+        };
 
         // If no existing image/color, just return the image
         if token_image.is_none() {
@@ -257,6 +1076,51 @@ impl<'a> Generator<'a> {
         Ok(token_image)
     }
 
+    /// Sets the scale of the layers of the token from the size of its first layer, reducing its
+    /// resolution to fit within any maximum size.
+    fn set_scale(&mut self, width: f32, height: f32) {
+        self.scale = match self.size {
+            Some(size) => (size as f32 / width.max(height)).min(1.0),
+            None => 1.0,
+        };
+    }
+
+    /// The size of a layer at the scale of the token.
+    fn scaled(&self, width: f32, height: f32) -> (u32, u32) {
+        (
+            ((width * self.scale).round() as u32).max(1),
+            ((height * self.scale).round() as u32).max(1),
+        )
+    }
+
+    fn generate_document_layer(
+        &mut self,
+        document: &mut svg::Document,
+        file: &Path,
+        vector: bool,
+    ) -> Result<()> {
+        let path = self
+            .source
+            .join(file)
+            .into_os_string()
+            .into_string()
+            .expect(PATH_TO_STRING_MSG);
+        if vector {
+            document.add_svg(self.caches.svg.get(&path)?);
+        } else {
+            // Embed the original file, using the cached image to determine its size
+            let image = self.caches.image.get(&path)?;
+            let (width, height) = (image.width(), image.height());
+            let mime_type = ImageFormat::from_path(&path)
+                .with_context(|| format!("unable to determine image format of {path}"))?
+                .to_mime_type();
+            let data = std::fs::read(&path).with_context(|| format!("unable to open {path}"))?;
+            document.add_image(&data, mime_type, width, height);
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn generate_text(
         &mut self,
         token_id: usize,
@@ -279,89 +1143,230 @@ impl<'a> Generator<'a> {
 
         // Initialise text
         let token_variables = HashMap::from([(ID.to_string(), token_id.to_string())]);
-        let text = strfmt::strfmt(&text, &token_variables)
+        let text = strfmt::strfmt(text, &token_variables)
             .expect("unable to name token {token} using the configured token external url format");
 
         let image = token_image.as_ref().expect(
             "an image is required before text can be written - check that the text layer is above some other image layer");
 
-        let scale = Scale::uniform(*height);
-        let text_size = text_size(scale, &font, &text);
-        let x = if *x < 0 {
+        // Text is positioned and sized at the scale of the token
+        let (x, y) = (
+            (*x as f32 * self.scale).round() as i32,
+            (*y as f32 * self.scale).round() as i32,
+        );
+        let scale = Scale::uniform(*height * self.scale);
+        let text_size = text_size(scale, font, &text);
+        let x = if x < 0 {
             (image.width() as i32 + x) - text_size.0
         } else {
-            *x
+            x
         };
         Ok(DynamicImage::ImageRgba8(draw_text(
-            image, color.rgba, x, *y, scale, &font, &text,
+            image, color.rgba, x, y, scale, font, &text,
         )))
     }
 
-    async fn generate_video(&mut self, image_path: &PathBuf, audio: &PathBuf) -> Result<PathBuf> {
+    fn audio_duration(&mut self, audio: &Path) -> Result<Duration> {
+        let audio_path = self
+            .source
+            .join(audio)
+            .into_os_string()
+            .into_string()
+            .expect(PATH_TO_STRING_MSG);
+        Ok(*self.caches.audio.get(&audio_path)?)
+    }
+
+    async fn generate_video(&mut self, image_path: &Path, audio: &Path) -> Result<PathBuf> {
         // Determine precise audio duration
+        trace!("determining audio track duration for precise output...");
+        let audio_duration = self.audio_duration(audio)?;
+        trace!("audio track duration is {}", audio_duration.hhmmssxxx());
         let audio_path = self
             .source
             .join(audio)
             .into_os_string()
             .into_string()
             .expect(PATH_TO_STRING_MSG);
-        let mut audio_duration: Option<&Duration> = None;
-        if let Some(extension) = audio.extension().and_then(|e| e.to_str()) {
-            if extension == "m4a" {
-                trace!("determining audio track duration for precise output...");
-                // Read file to determine audio length
-                audio_duration = Some(
-                    self.caches
-                        .audio
-                        .get(&audio_path)
-                        .expect("could not get cached audio"),
-                );
-                trace!(
-                    "audio track duration is {}",
-                    audio_duration.unwrap().hhmmssxxx()
-                );
+
+        // Generate video, retrying failed attempts as configured
+        let mut video_path = image_path.to_path_buf();
+        video_path.set_extension(&self.video.container);
+        let mut attempt = 0;
+        loop {
+            match Self::encode_video(
+                self.video,
+                image_path,
+                &audio_path,
+                &video_path,
+                audio_duration,
+            )
+            .await
+            {
+                Ok(()) => return Ok(video_path),
+                Err(e) => {
+                    // Never leave a missing or truncated video behind
+                    if video_path.exists() {
+                        std::fs::remove_file(&video_path).with_context(|| {
+                            format!(
+                                "could not remove failed video '{}'",
+                                video_path.to_str().expect(PATH_TO_STRING_MSG)
+                            )
+                        })?;
+                    }
+                    if attempt >= self.retries {
+                        return Err(e);
+                    }
+                    attempt += 1;
+                    warn!(
+                        "video generation failed, retrying ({attempt}/{})...: {e:#}",
+                        self.retries
+                    );
+                }
             }
         }
+    }
 
+    async fn encode_video(
+        video: &Video,
+        image_path: &Path,
+        audio_path: &str,
+        video_path: &Path,
+        audio_duration: Duration,
+    ) -> Result<()> {
         // Build ffmpeg command
-        let mut video_path = image_path.clone();
-        video_path.set_extension("mp4");
-        let audio_duration =
-            audio_duration.map_or("".to_string(), |d| format!("{}ms", d.as_millis()));
-        let mut output = ffmpeg_cli::File::new(&video_path.to_str().expect(PATH_TO_STRING_MSG))
-            .option(Parameter::KeyValue("acodec", "aac"))
-            .option(Parameter::KeyValue("vcodec", "libx264"))
-            .option(Parameter::KeyValue("pix_fmt", "yuv420p")); // Required for compatibility
-        if audio_duration != "" {
-            output = output.option(Parameter::KeyValue("t", &audio_duration));
+        let video_file = video_path.to_str().expect(PATH_TO_STRING_MSG);
+        let duration = format!("{}ms", audio_duration.as_millis());
+        let framerate = video.framerate.to_string();
+        let crf = video.crf.map(|crf| crf.to_string());
+        // ffmpeg only keeps the last video filter option, so scaling is merged into any filters
+        // passed as additional arguments
+        let filters: Vec<String> = video
+            .scale
+            .iter()
+            .map(|scale| format!("scale={scale}"))
+            .chain(
+                video
+                    .args
+                    .iter()
+                    .filter(|(key, _)| VIDEO_FILTER_ARGS.contains(&key.as_str()))
+                    .filter_map(|(_, value)| value.clone()),
+            )
+            .collect();
+        let filters = filters.join(",");
+        let mut output = ffmpeg_cli::File::new(video_file)
+            .option(Parameter::KeyValue("acodec", &video.audio_codec))
+            .option(Parameter::KeyValue("vcodec", &video.video_codec))
+            .option(Parameter::KeyValue("pix_fmt", &video.pixel_format))
+            .option(Parameter::KeyValue("t", &duration));
+        if let Some(bitrate) = &video.bitrate {
+            output = output.option(Parameter::KeyValue("b:v", bitrate));
+        }
+        if let Some(crf) = &crf {
+            output = output.option(Parameter::KeyValue("crf", crf));
+        }
+        if let Some(preset) = &video.preset {
+            output = output.option(Parameter::KeyValue("preset", preset));
+        }
+        if !filters.is_empty() {
+            output = output.option(Parameter::KeyValue("vf", &filters));
+        }
+        if let Some(audio_bitrate) = &video.audio_bitrate {
+            output = output.option(Parameter::KeyValue("b:a", audio_bitrate));
+        }
+        for (key, value) in &video.args {
+            if VIDEO_FILTER_ARGS.contains(&key.as_str()) {
+                continue;
+            }
+            output = output.option(match value {
+                Some(value) => Parameter::KeyValue(key, value),
+                None => Parameter::Single(key),
+            });
         }
         let builder = FfmpegBuilder::new()
             .stderr(Stdio::piped())
             .option(Parameter::Single("nostdin"))
+            .option(Parameter::Single("y"))
             .option(Parameter::KeyValue("loop", "1"))
             .input(
-                ffmpeg_cli::File::new(&image_path.to_str().expect(PATH_TO_STRING_MSG))
-                    .option(Parameter::KeyValue("framerate", "1")) // Single image so only single frame
-                    .option(Parameter::KeyValue("colorspace", "bt709")), // Preserve colors as best as possible
+                ffmpeg_cli::File::new(image_path.to_str().expect(PATH_TO_STRING_MSG))
+                    .option(Parameter::KeyValue("framerate", &framerate))
+                    .option(Parameter::KeyValue("colorspace", &video.colorspace)),
             )
-            .input(ffmpeg_cli::File::new(&audio_path))
+            .input(ffmpeg_cli::File::new(audio_path))
             .output(output);
 
         // Run ffmpeg command
         let current = Instant::now();
         trace!("generating video from image and audio...");
-        let ffmpeg = builder.run().await.expect("unable to run ffmpeg");
-        ffmpeg
+        let ffmpeg = builder
+            .run()
+            .await
+            .map_err(|e| anyhow!("unable to run ffmpeg: {e}"))?;
+        let output = ffmpeg
             .process
             .wait_with_output()
             .with_context(|| "could not generate the video")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let lines: Vec<&str> = stderr.trim_end().lines().collect();
+            return Err(anyhow!(
+                "ffmpeg failed to generate '{video_file}' ({}):\n{}",
+                output.status,
+                lines[lines.len().saturating_sub(FFMPEG_STDERR_LINES)..].join("\n")
+            ));
+        }
+
+        // Verify the resulting video
+        let size = std::fs::metadata(video_path)
+            .with_context(|| format!("ffmpeg did not produce a video at '{video_file}'"))?
+            .len();
+        if size == 0 {
+            return Err(anyhow!("ffmpeg produced an empty video at '{video_file}'"));
+        }
+        if MP4_CONTAINERS.contains(&video.container.to_ascii_lowercase().as_str()) {
+            let video_duration = video_duration(video_path, size).with_context(|| {
+                format!("ffmpeg did not produce a valid video at '{video_file}'")
+            })?;
+            if video_duration.abs_diff(audio_duration) > VIDEO_DURATION_TOLERANCE {
+                return Err(anyhow!(
+                    "'{video_file}' has a duration of {} but {} was expected",
+                    video_duration.hhmmssxxx(),
+                    audio_duration.hhmmssxxx()
+                ));
+            }
+        }
 
         trace!(
-            "successfully generated {} in {}",
-            video_path.to_str().expect(PATH_TO_STRING_MSG),
+            "successfully generated {video_file} in {}",
             current.elapsed().hhmmssxxx()
         );
-        Ok(video_path)
+        Ok(())
+    }
+
+    fn remove_media(&self, token: usize) {
+        for path in provenance::media_files(&self.media, token, &self.video.container) {
+            if let Err(e) = std::fs::remove_file(&path) {
+                error!(
+                    "error removing {}: {e}",
+                    path.to_str().expect(PATH_TO_STRING_MSG)
+                )
+            }
+        }
+    }
+
+    fn save_document(
+        &self,
+        token: usize,
+        document: &svg::Document,
+        background: Option<&Color>,
+    ) -> Result<PathBuf> {
+        let document_path = self.media.join(format!("{token}.svg"));
+        debug!(
+            "saving token {token} media as '{}'",
+            document_path.to_str().expect(PATH_TO_STRING_MSG)
+        );
+        document.save(&document_path, background)?;
+        Ok(document_path)
     }
 
     fn save_image(&self, token: usize, token_image: DynamicImage) -> Result<PathBuf> {
@@ -370,7 +1375,7 @@ impl<'a> Generator<'a> {
         {
             let image_path = image_path.to_str().expect(PATH_TO_STRING_MSG);
             debug!("saving token {token} media as '{image_path}'");
-            if let Err(e) = token_image.save(&image_path) {
+            if let Err(e) = token_image.save(image_path) {
                 error!("error saving {image_path}: {e}")
             }
         }
@@ -379,20 +1384,16 @@ impl<'a> Generator<'a> {
     }
 
     fn save_metadata(
-        &self,
+        &mut self,
         token: usize,
+        dna: &str,
         attributes: Vec<metadata::Attribute>,
         background_color: Option<&str>,
         image_path: PathBuf,
         video_path: Option<PathBuf>,
     ) -> Result<()> {
         // Generate media paths relative to output folder
-        let media = self
-            .media
-            .components()
-            .last()
-            .expect("could not get last component from path");
-        let media_path = Path::new("/").join(&media);
+        let media_path = self.media_path();
         let image_name = image_path
             .file_name()
             .expect("could not get image file name");
@@ -401,7 +1402,7 @@ impl<'a> Generator<'a> {
             .to_str()
             .expect(PATH_TO_STRING_MSG)
             .to_string();
-        let animation_url = video_path.map(|p| {
+        let animation_url = video_path.as_ref().map(|p| {
             media_path
                 .join(p.file_name().expect("could not get video file name"))
                 .to_str()
@@ -410,29 +1411,422 @@ impl<'a> Generator<'a> {
         });
 
         // Create metadata
-        let token_variables = HashMap::from([(ID.to_string(), token.to_string())]);
-        let token_metadata = metadata::Metadata {
-            id: token,
-            name: strfmt::strfmt(&self.name, &token_variables).with_context(|| {
-                "unable to name token {token} using the configured token name format"
-            })?,
-            description: &self.description,
-            image,
-            external_url: self.external_url.as_ref().map(|url| {
-                strfmt::strfmt(&url, &token_variables).expect(
-                    "unable to name token {token} using the configured token external url format",
+        let mut token_variables: HashMap<String, String> = attributes
+            .iter()
+            .map(|attribute| {
+                let (name, value) = attribute.pair();
+                let value = match value {
+                    serde_json::Value::String(value) => value,
+                    value => value.to_string(),
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+        token_variables.insert(ID.to_string(), token.to_string());
+        token_variables.insert(DNA.to_string(), dna.to_string());
+        if let Some(edition) = self.edition {
+            token_variables.insert(EDITION.to_string(), edition.to_string());
+        }
+        let name = strfmt::strfmt(self.name, &token_variables).with_context(|| {
+            "unable to name token {token} using the configured token name format"
+        })?;
+        let external_url = self.external_url.as_ref().map(|url| {
+            strfmt::strfmt(url, &token_variables).expect(
+                "unable to name token {token} using the configured token external url format",
+            )
+        });
+        match self.standard {
+            Standard::OpenSea => self.write_metadata(
+                token,
+                dna,
+                &token_variables,
+                &metadata::Metadata {
+                    id: token,
+                    name,
+                    description: self.description,
+                    image,
+                    external_url,
+                    attributes,
+                    background_color: background_color.map(|color| color.replace('#', "")),
+                    animation_url,
+                    youtube_url: None,
+                },
+            ),
+            Standard::Erc1155 => self.write_metadata(
+                token,
+                dna,
+                &token_variables,
+                &metadata::erc1155::Metadata {
+                    name,
+                    decimals: self.erc1155.decimals,
+                    description: self.description,
+                    image,
+                    animation_url,
+                    external_url,
+                    background_color: background_color.map(|color| color.replace('#', "")),
+                    attributes,
+                    properties: &self.erc1155.properties,
+                },
+            ),
+            Standard::Tzip21 => {
+                let mut formats = vec![metadata::tzip21::Format {
+                    uri: image.clone(),
+                    mime_type: metadata::mime_type(&image_path),
+                }];
+                if let (Some(uri), Some(video_path)) = (&animation_url, &video_path) {
+                    formats.push(metadata::tzip21::Format {
+                        uri: uri.clone(),
+                        mime_type: metadata::mime_type(video_path),
+                    });
+                }
+                self.write_metadata(
+                    token,
+                    dna,
+                    &token_variables,
+                    &metadata::tzip21::Metadata {
+                        name,
+                        symbol: self.tzip21.symbol.as_deref(),
+                        description: self.description,
+                        decimals: 0,
+                        is_boolean_amount: true,
+                        artifact_uri: animation_url.unwrap_or_else(|| image.clone()),
+                        display_uri: image.clone(),
+                        thumbnail_uri: image,
+                        external_uri: external_url,
+                        formats,
+                        creators: &self.tzip21.creators,
+                        rights: self.tzip21.rights.as_deref(),
+                        tags: &self.tzip21.tags,
+                        attributes: attributes
+                            .iter()
+                            .map(|attribute| {
+                                let (name, value) = attribute.pair();
+                                metadata::tzip21::Attribute {
+                                    name,
+                                    value: match value {
+                                        serde_json::Value::String(value) => value,
+                                        value => value.to_string(),
+                                    },
+                                }
+                            })
+                            .collect(),
+                    },
                 )
-            }),
-            attributes,
-            background_color: background_color.map(|color| color.replace("#", "")),
-            animation_url,
-            youtube_url: None,
+            }
+            Standard::Cip25 => {
+                let cip25 = self
+                    .cip25
+                    .expect("expected cip25 configuration for the cip25 standard");
+                let asset_name =
+                    strfmt::strfmt(&cip25.asset_name, &token_variables).with_context(|| {
+                        "unable to name token {token} using the configured asset name format"
+                    })?;
+                let mut files = vec![metadata::cip25::File {
+                    name: name.clone(),
+                    media_type: metadata::mime_type(&image_path),
+                    src: metadata::cip25::chunk(&image),
+                }];
+                if let (Some(uri), Some(video_path)) = (&animation_url, &video_path) {
+                    files.push(metadata::cip25::File {
+                        name: name.clone(),
+                        media_type: metadata::mime_type(video_path),
+                        src: metadata::cip25::chunk(uri),
+                    });
+                }
+                debug!("adding token {token} metadata as asset '{asset_name}'");
+                let asset = metadata::cip25::Asset {
+                    name,
+                    image: metadata::cip25::chunk(&image),
+                    media_type: metadata::mime_type(&image_path),
+                    description: metadata::cip25::chunk(self.description),
+                    files,
+                    attributes: attributes
+                        .iter()
+                        .map(|attribute| {
+                            let (name, value) = attribute.pair();
+                            (name.to_string(), value)
+                        })
+                        .collect(),
+                };
+                let asset = metadata::customize(
+                    serde_json::to_value(asset)?,
+                    dna,
+                    self.custom_metadata,
+                    &token_variables,
+                    self.standard,
+                )?;
+                self.cip25_assets.insert(asset_name, asset);
+                Ok(())
+            }
+            Standard::Metaplex => {
+                let metaplex = self
+                    .metaplex
+                    .expect("expected metaplex configuration for the metaplex standard");
+                let mut files = vec![metadata::metaplex::File {
+                    uri: image.clone(),
+                    mime_type: metadata::mime_type(&image_path),
+                }];
+                if let (Some(uri), Some(video_path)) = (&animation_url, &video_path) {
+                    files.push(metadata::metaplex::File {
+                        uri: uri.clone(),
+                        mime_type: metadata::mime_type(video_path),
+                    });
+                }
+                self.write_metadata(
+                    token,
+                    dna,
+                    &token_variables,
+                    &metadata::metaplex::Metadata {
+                        name,
+                        symbol: &metaplex.symbol,
+                        description: self.description,
+                        seller_fee_basis_points: metaplex.seller_fee_basis_points,
+                        image,
+                        animation_url: animation_url.clone(),
+                        external_url,
+                        attributes,
+                        properties: metadata::metaplex::Properties {
+                            files,
+                            category: if animation_url.is_some() {
+                                "video"
+                            } else {
+                                "image"
+                            },
+                            creators: metaplex
+                                .creators
+                                .iter()
+                                .map(|c| metadata::metaplex::Creator {
+                                    address: &c.address,
+                                    share: c.share,
+                                })
+                                .collect(),
+                        },
+                        collection: metaplex.collection.as_ref().map(|c| {
+                            metadata::metaplex::Collection {
+                                name: &c.name,
+                                family: c.family.as_deref(),
+                            }
+                        }),
+                    },
+                )
+            }
+        }
+    }
+
+    fn save_cip25_metadata(&self) -> Result<()> {
+        let cip25 = self
+            .cip25
+            .expect("expected cip25 configuration for the cip25 standard");
+        let metadata_path = self
+            .metadata
+            .join(metadata::CIP25_FILE_NAME)
+            .into_os_string()
+            .into_string()
+            .expect(PATH_TO_STRING_MSG);
+        debug!("saving combined cip25 metadata as '{metadata_path}'");
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&metadata_path)?;
+        serde_json::to_writer(
+            file,
+            &metadata::cip25::Metadata {
+                policy_id: &cip25.policy_id,
+                version: &cip25.version,
+                assets: &self.cip25_assets,
+            },
+        )
+        .with_context(|| format!("error saving {metadata_path}"))
+    }
+
+    /// Reads the assets of the combined cip25 metadata within the metadata directory (if any), so
+    /// that tokens which are not re-rendered retain their metadata.
+    fn load_cip25_assets(&self) -> Result<IndexMap<String, serde_json::Value>> {
+        let cip25 = self
+            .cip25
+            .expect("expected cip25 configuration for the cip25 standard");
+        let metadata_path = self.metadata.join(metadata::CIP25_FILE_NAME);
+        if !metadata_path.is_file() {
+            return Ok(IndexMap::new());
+        }
+        let metadata_path = metadata_path.to_str().expect(PATH_TO_STRING_MSG);
+        trace!("reading combined cip25 metadata from '{metadata_path}'...");
+        let file =
+            File::open(metadata_path).with_context(|| format!("unable to open {metadata_path}"))?;
+        let mut combined: serde_json::Value = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("unable to read {metadata_path}"))?;
+        Ok(
+            match combined[metadata::cip25::LABEL][&cip25.policy_id].take() {
+                serde_json::Value::Object(assets) => assets.into_iter().collect(),
+                _ => IndexMap::new(),
+            },
+        )
+    }
+
+    fn save_hidden_metadata(&self, hidden: &Hidden, tokens: &[usize]) -> Result<()> {
+        // Copy the placeholder image alongside token media, shared by all hidden metadata
+        let image_name = self.copy_media(&hidden.image, HIDDEN_IMAGE_NAME)?;
+        let image = self
+            .media_path()
+            .join(image_name)
+            .to_str()
+            .expect(PATH_TO_STRING_MSG)
+            .to_string();
+        let description = hidden.description.as_deref().unwrap_or(self.description);
+
+        let hidden_path = self
+            .metadata
+            .parent()
+            .expect("could not get output folder")
+            .join(metadata::HIDDEN_DIRECTORY);
+        std::fs::create_dir_all(&hidden_path).with_context(|| {
+            format!(
+                "could not create hidden metadata output directory {}",
+                hidden_path.to_str().expect(PATH_TO_STRING_MSG)
+            )
+        })?;
+        for &token in tokens {
+            let token_variables = HashMap::from([(ID.to_string(), token.to_string())]);
+            let name = strfmt::strfmt(&hidden.name, &token_variables).with_context(|| {
+                "unable to name token {token} using the configured hidden name format"
+            })?;
+            let metadata_path = hidden_path
+                .join(metadata::file_name(self.standard, token))
+                .into_os_string()
+                .into_string()
+                .expect(PATH_TO_STRING_MSG);
+            debug!("saving token {token} hidden metadata as '{metadata_path}'");
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&metadata_path)?;
+            serde_json::to_writer(
+                file,
+                &metadata::hidden::Metadata {
+                    name,
+                    description,
+                    image: &image,
+                },
+            )
+            .with_context(|| format!("error saving {metadata_path}"))?;
+        }
+        Ok(())
+    }
+
+    fn save_collection_metadata(&self, collection: &Collection) -> Result<()> {
+        // Copy collection media alongside token media, referenced relative to output folder
+        let media = Path::new("/").join(
+            self.media
+                .components()
+                .next_back()
+                .expect("could not get last component from path"),
+        );
+        let copy_media = |file: &Option<PathBuf>, name: &str| -> Result<Option<String>> {
+            let Some(file) = file else {
+                return Ok(None);
+            };
+            let file_name = self.copy_media(file, name)?;
+            Ok(Some(
+                media
+                    .join(file_name)
+                    .to_str()
+                    .expect(PATH_TO_STRING_MSG)
+                    .to_string(),
+            ))
         };
+        let image = copy_media(&collection.image, COLLECTION_IMAGE_NAME)?;
+        let banner_image = copy_media(&collection.banner, COLLECTION_BANNER_NAME)?;
+
+        let metadata_path = self
+            .metadata
+            .parent()
+            .expect("could not get output folder")
+            .join(metadata::COLLECTION_FILE_NAME)
+            .into_os_string()
+            .into_string()
+            .expect(PATH_TO_STRING_MSG);
+        debug!("saving collection metadata as '{metadata_path}'");
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&metadata_path)?;
+        serde_json::to_writer(
+            file,
+            &metadata::collection::Metadata {
+                name: &collection.name,
+                description: collection
+                    .description
+                    .as_deref()
+                    .unwrap_or(self.description),
+                image,
+                banner_image,
+                external_link: collection.external_link.as_deref(),
+                seller_fee_basis_points: collection.seller_fee_basis_points,
+                fee_recipient: collection.fee_recipient.as_deref(),
+            },
+        )
+        .with_context(|| format!("error saving {metadata_path}"))
+    }
+
+    /// Copies a file from the source directory into the media folder, using the specified name
+    /// along with the extension of the file, and returns the resulting file name.
+    fn copy_media(&self, file: &Path, name: &str) -> Result<PathBuf> {
+        let source = self.source.join(file);
+        let mut file_name = PathBuf::from(name);
+        if let Some(extension) = source.extension() {
+            file_name.set_extension(extension);
+        }
+        let destination = self.media.join(&file_name);
+        trace!(
+            "copying '{}' to '{}'",
+            source.to_str().expect(PATH_TO_STRING_MSG),
+            destination.to_str().expect(PATH_TO_STRING_MSG)
+        );
+        std::fs::copy(&source, &destination).with_context(|| {
+            format!(
+                "unable to copy {} to media",
+                source.to_str().expect(PATH_TO_STRING_MSG)
+            )
+        })?;
+        Ok(file_name)
+    }
+
+    /// The path by which token metadata references media, relative to the output folder.
+    fn media_path(&self) -> PathBuf {
+        let media = self
+            .media
+            .components()
+            .next_back()
+            .expect("could not get last component from path");
+        match self.standard {
+            // Candy Machine expects media alongside metadata, referenced by file name only
+            Standard::Metaplex => PathBuf::new(),
+            Standard::OpenSea | Standard::Erc1155 | Standard::Tzip21 | Standard::Cip25 => {
+                Path::new("/").join(media)
+            }
+        }
+    }
 
-        // Save metadata
+    fn write_metadata<T: Serialize>(
+        &self,
+        token: usize,
+        dna: &str,
+        token_variables: &HashMap<String, String>,
+        token_metadata: &T,
+    ) -> Result<()> {
+        let token_metadata = metadata::customize(
+            serde_json::to_value(token_metadata)?,
+            dna,
+            self.custom_metadata,
+            token_variables,
+            self.standard,
+        )?;
         let metadata_path = self
             .metadata
-            .join(token.to_string())
+            .join(metadata::file_name(self.standard, token))
             .into_os_string()
             .into_string()
             .expect(PATH_TO_STRING_MSG);
@@ -449,3 +1843,87 @@ impl<'a> Generator<'a> {
         Ok(())
     }
 }
+
+fn metadata_attribute<'b>(
+    attribute: &'b Attribute,
+    value: AttributeValue<'b>,
+    option: &'b AttributeOption,
+) -> Option<metadata::Attribute<'b>> {
+    // Determine numeric value, as drawn from the range for each token as required
+    let number = match option {
+        AttributeOption::Number { number, .. } => Some(*number),
+        AttributeOption::Range { .. } => value.number.map(|number| number as f64),
+        _ => None,
+    };
+
+    let trait_type = attribute.trait_type();
+    let max_value = attribute.max_value;
+    match (attribute.display_type, number) {
+        (None, None) => Some(metadata::Attribute::String {
+            trait_type,
+            value: Cow::Borrowed(option.value().unwrap_or(value.name)),
+        }),
+        // Numeric attributes are omitted from tokens without a numeric value
+        (Some(_), None) => None,
+        (None | Some(DisplayType::Number), Some(number)) => Some(metadata::Attribute::Number {
+            trait_type,
+            value: number as usize,
+            max_value,
+        }),
+        (Some(DisplayType::BoostPercentage), Some(number)) => {
+            Some(metadata::Attribute::BoostPercentage {
+                trait_type,
+                value: number as f32,
+                max_value,
+            })
+        }
+        (Some(DisplayType::BoostNumber), Some(number)) => Some(metadata::Attribute::BoostNumber {
+            trait_type,
+            value: number as f32,
+            max_value,
+        }),
+        (Some(DisplayType::Date), Some(number)) => Some(metadata::Attribute::Date {
+            trait_type,
+            value: number as i64,
+        }),
+    }
+}
+
+/// Adds a metadata attribute to those of a token, merging string traits which share a trait type
+/// (e.g. the back and front layers of a hat) into a single trait. Values of empty (none) options
+/// are only retained whilst no other attribute contributes a value to the trait.
+fn merge_attribute<'b>(
+    attributes: &mut Vec<(metadata::Attribute<'b>, bool)>,
+    attribute: metadata::Attribute<'b>,
+    none: bool,
+) {
+    let metadata::Attribute::String { trait_type, value } = &attribute else {
+        attributes.push((attribute, none));
+        return;
+    };
+    let existing = attributes.iter_mut().find(
+        |(a, _)| matches!(a, metadata::Attribute::String { trait_type: t, .. } if t == trait_type),
+    );
+    match existing {
+        Some((
+            metadata::Attribute::String {
+                value: existing, ..
+            },
+            existing_none,
+        )) => {
+            if *existing_none && !none {
+                *existing = value.clone();
+                *existing_none = false;
+            } else if !none && !existing.split(MERGED_VALUE_SEPARATOR).any(|v| v == value) {
+                *existing = Cow::Owned(format!("{existing}{MERGED_VALUE_SEPARATOR}{value}"));
+            }
+        }
+        _ => attributes.push((attribute, none)),
+    }
+}
+
+fn video_duration(path: &Path, size: u64) -> Result<Duration> {
+    let file = File::open(path).with_context(|| "error opening video file")?;
+    let reader = mp4::Mp4Reader::read_header(BufReader::new(file), size)?;
+    Ok(reader.duration())
+}
@@ -0,0 +1,140 @@
+use crate::config::{AttributeOption, Edition};
+use crate::random::{self, AttributeValue, Token};
+use crate::{Config, PATH_TO_STRING_MSG};
+use anyhow::{anyhow, Context, Result};
+use indexmap::IndexMap;
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+// The file name of the generation plan, written within the output directory
+const PLAN_FILE_NAME: &str = "plan.json";
+
+/// The planned attribute values of a token, as randomly generated prior to creating its media.
+#[derive(Serialize)]
+struct Entry<'a> {
+    token: usize,
+    // The name of the edition of the token, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edition: Option<&'a str>,
+    // The hash of the chosen attribute values, in attribute order.
+    dna: String,
+    // The chosen value of each attribute, in attribute order.
+    attributes: IndexMap<&'a str, &'a str>,
+    // The number drawn for each attribute whose chosen option is a range, in attribute order.
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    numbers: IndexMap<&'a str, i64>,
+}
+
+/// A planned token, as read from the generation plan.
+#[derive(Deserialize)]
+pub(crate) struct Planned {
+    pub(crate) token: usize,
+    /// The name of the edition of the token, if any.
+    #[serde(default)]
+    pub(crate) edition: Option<String>,
+    pub(crate) dna: String,
+    /// The chosen value of each attribute, by attribute name.
+    pub(crate) attributes: IndexMap<String, String>,
+    /// The number drawn for each attribute whose chosen option is a range, by attribute name.
+    #[serde(default)]
+    pub(crate) numbers: IndexMap<String, i64>,
+}
+
+/// Saves the planned attribute values and dna of each token within the output directory.
+pub(crate) fn save(output: &Path, tokens: &[(usize, Token, Option<&Edition>)]) -> Result<()> {
+    let plan: Vec<Entry> = tokens
+        .iter()
+        .map(|(number, token, edition)| Entry {
+            token: *number,
+            edition: edition.map(|e| e.name.as_str()),
+            dna: random::dna(token),
+            attributes: token
+                .iter()
+                .map(|(attribute, value, _)| (attribute.name.as_str(), value.name))
+                .collect(),
+            numbers: token
+                .iter()
+                .filter_map(|(attribute, value, _)| {
+                    value.number.map(|number| (attribute.name.as_str(), number))
+                })
+                .collect(),
+        })
+        .collect();
+
+    let plan_path = output
+        .join(PLAN_FILE_NAME)
+        .into_os_string()
+        .into_string()
+        .expect(PATH_TO_STRING_MSG);
+    debug!("saving generation plan as '{plan_path}'");
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&plan_path)?;
+    serde_json::to_writer_pretty(file, &plan).with_context(|| format!("error saving {plan_path}"))
+}
+
+/// Resolves the planned tokens against the configuration, failing if the plan no longer matches
+/// the configured attributes, options or editions.
+pub(crate) fn tokens<'a>(
+    config: &'a Config,
+    plan: &[Planned],
+) -> Result<Vec<(usize, Token<'a>, Option<&'a Edition>)>> {
+    plan.iter()
+        .map(|planned| {
+            let token = planned.token;
+            let edition = match &planned.edition {
+                Some(name) => Some(
+                    config
+                        .editions
+                        .iter()
+                        .find(|e| &e.name == name)
+                        .ok_or_else(|| anyhow!("edition '{name}' of token {token} is not configured"))?,
+                ),
+                None => None,
+            };
+            let attributes = config
+                .attributes
+                .iter()
+                .map(|attribute| {
+                    let value = planned.attributes.get(&attribute.name).ok_or_else(|| {
+                        anyhow!("token {token} has no planned value of the '{}' attribute", attribute.name)
+                    })?;
+                    let (name, option) = attribute.options.get_key_value(value).ok_or_else(|| {
+                        anyhow!("planned value '{value}' of token {token} is not an option of the '{}' attribute", attribute.name)
+                    })?;
+                    let number = match option {
+                        AttributeOption::Range { .. } => {
+                            Some(*planned.numbers.get(&attribute.name).ok_or_else(|| {
+                                anyhow!("token {token} has no planned number of the '{}' attribute", attribute.name)
+                            })?)
+                        }
+                        _ => None,
+                    };
+                    Ok((attribute, AttributeValue { name, number }, option))
+                })
+                .collect::<Result<Token>>()?;
+            Ok((token, attributes, edition))
+        })
+        .collect()
+}
+
+/// Whether the generation plan exists within the output directory.
+pub(crate) fn exists(output: &Path) -> bool {
+    output.join(PLAN_FILE_NAME).is_file()
+}
+
+/// Loads the planned attribute values of each token from the output directory.
+pub(crate) fn load(output: &Path) -> Result<Vec<Planned>> {
+    let plan_path = output.join(PLAN_FILE_NAME);
+    let plan_path = plan_path.to_str().expect(PATH_TO_STRING_MSG);
+    trace!("reading generation plan from '{plan_path}'...");
+    let file = File::open(plan_path).with_context(|| {
+        format!("unable to read generation plan from {plan_path} - generate the collection and try again")
+    })?;
+    serde_json::from_reader(file)
+        .with_context(|| format!("unable to read generation plan from {plan_path}"))
+}
@@ -0,0 +1,80 @@
+use crate::config::Color;
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use resvg::usvg::{Tree, WriteOptions};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// A svg document composed from the layers of a token.
+pub(crate) struct Document {
+    size: Option<(u32, u32)>,
+    layers: Vec<String>,
+}
+
+impl Document {
+    pub(crate) fn new() -> Self {
+        Self {
+            size: None,
+            layers: Vec::new(),
+        }
+    }
+
+    /// The size of the document, as determined by the first image layer.
+    pub(crate) fn size(&self) -> Option<(u32, u32)> {
+        self.size
+    }
+
+    /// Adds a raster image layer, embedded as a data URI.
+    pub(crate) fn add_image(&mut self, data: &[u8], mime_type: &str, width: u32, height: u32) {
+        let (width, height) = *self.size.get_or_insert((width, height));
+        self.layers.push(format!(
+            r#"<image x="0" y="0" width="{width}" height="{height}" preserveAspectRatio="none" href="data:{mime_type};base64,{}"/>"#,
+            STANDARD.encode(data)
+        ));
+    }
+
+    /// Adds a vector layer, scaled to the size of the document.
+    pub(crate) fn add_svg(&mut self, tree: &Tree) {
+        let tree_size = tree.size();
+        let (width, height) = *self.size.get_or_insert((
+            tree_size.width().round() as u32,
+            tree_size.height().round() as u32,
+        ));
+
+        // Prefix element ids so that layers cannot clash with each other
+        let svg = tree.to_string(&WriteOptions {
+            id_prefix: Some(format!("l{}-", self.layers.len())),
+            ..WriteOptions::default()
+        });
+        self.layers.push(format!(
+            r#"<g transform="scale({} {})">{svg}</g>"#,
+            width as f32 / tree_size.width(),
+            height as f32 / tree_size.height()
+        ));
+    }
+
+    pub(crate) fn save(&self, path: &Path, background: Option<&Color>) -> Result<()> {
+        let (width, height) = self
+            .size
+            .ok_or_else(|| anyhow!("an image layer is required to determine the svg size"))?;
+        let mut file = File::create(path)?;
+        write!(
+            file,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        )?;
+        if let Some(color) = background {
+            let [r, g, b, a] = color.rgba.0;
+            write!(
+                file,
+                r#"<rect width="100%" height="100%" fill="rgb({r},{g},{b})" fill-opacity="{}"/>"#,
+                a as f32 / 255.0
+            )?;
+        }
+        for layer in &self.layers {
+            write!(file, "{layer}")?;
+        }
+        write!(file, "</svg>").with_context(|| "unable to write svg")
+    }
+}
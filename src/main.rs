@@ -1,4 +1,6 @@
-use crate::config::Config;
+use crate::config::{Config, Override, Selection};
+use crate::generation::FailurePolicy;
+use crate::watch::Watcher;
 use anyhow::{Context, Result};
 use log::{error, trace};
 use std::path::PathBuf;
@@ -9,8 +11,16 @@ mod config;
 mod deployment;
 mod generation;
 mod metadata;
+mod mosaic;
 mod output;
+mod preview;
+mod provenance;
 mod random;
+mod reveal;
+mod serve;
+mod sheet;
+mod traits;
+mod watch;
 
 const PATH_TO_STRING_MSG: &str = "could not convert path to string";
 
@@ -28,18 +38,50 @@ async fn main() -> Result<()> {
     match &command {
         Command::Generate {
             config,
+            set,
             output,
             media,
             metadata,
+            retries,
+            on_failure,
+            watch,
             source,
             ..
         } => {
-            // Read config from config.json
-            let config = config::load(source, config)?;
-            // Initialise output directories
-            output::init(source, output, media, metadata)?;
-            // Generate tokens
-            generation::generate(source, output, media, metadata, config).await
+            let watcher = match watch {
+                true => Some(Watcher::new(source, &[output])?),
+                false => None,
+            };
+            // The config the output was last generated from, if generated successfully
+            let mut previous = None;
+            loop {
+                // Read config from config.json
+                let config = watch::load(source, config, set, watcher.as_ref())?;
+                // Generate tokens, returning once the config changes when watching
+                let generated = generation::generate(
+                    source,
+                    output,
+                    media,
+                    metadata,
+                    &config,
+                    previous.as_ref(),
+                    *retries,
+                    *on_failure,
+                    watcher.as_ref(),
+                )
+                .await;
+                let Some(watcher) = &watcher else {
+                    return generated;
+                };
+                previous = match generated {
+                    Ok(()) => Some(config),
+                    Err(e) => {
+                        error!("{e:#}");
+                        watcher.wait_config()?;
+                        None
+                    }
+                };
+            }
         }
         Command::Deploy {
             output,
@@ -58,6 +100,151 @@ async fn main() -> Result<()> {
                 .with_context(|| format!("unable to parse {base_uri} as a url"))?;
             deployment::deploy(source, output, metadata, &base_uri)
         }
+        Command::Serve {
+            config,
+            set,
+            output,
+            media,
+            metadata,
+            address,
+            port,
+            source,
+            ..
+        } => {
+            let config = config::load(source, config, set)?;
+            serve::serve(source, output, media, metadata, &config, address, *port)
+        }
+        Command::TraitSheets {
+            config,
+            set,
+            output,
+            media,
+            metadata,
+            traits,
+            base,
+            columns,
+            tile_size,
+            font,
+            source,
+            ..
+        } => {
+            let config = config::load(source, config, set)?;
+            traits::export(
+                source,
+                output,
+                media,
+                metadata,
+                traits,
+                config,
+                base,
+                traits::Options {
+                    columns: *columns,
+                    tile_size: *tile_size,
+                    font: font.as_deref(),
+                },
+            )
+            .await
+        }
+        Command::VerifyProvenance {
+            config,
+            set,
+            output,
+            media,
+            source,
+            ..
+        } => {
+            let config = config::load(source, config, set)?;
+            provenance::verify(source, output, media, &config.video.container)
+        }
+        Command::Mosaic {
+            output,
+            media,
+            metadata,
+            mosaic,
+            filter,
+            columns,
+            rows,
+            tile_size,
+            padding,
+            labels,
+            font,
+            source,
+            ..
+        } => mosaic::mosaic(
+            source,
+            output,
+            media,
+            metadata,
+            mosaic,
+            filter,
+            mosaic::Options {
+                columns: *columns,
+                rows: *rows,
+                tile_size: *tile_size,
+                padding: *padding,
+                labels: *labels,
+                font: font.as_deref(),
+            },
+        ),
+        Command::Preview {
+            config,
+            set,
+            count,
+            columns,
+            tile_size,
+            font,
+            watch,
+            source,
+            ..
+        } => {
+            let watcher = match watch {
+                true => Some(Watcher::new(source, &[])?),
+                false => None,
+            };
+            loop {
+                let config = watch::load(source, config, set, watcher.as_ref())?;
+                // Previews again once the config changes when watching
+                let previewed = preview::preview(
+                    source,
+                    config,
+                    *count,
+                    *columns,
+                    *tile_size,
+                    font.as_deref(),
+                    watcher.as_ref(),
+                )
+                .await;
+                let Some(watcher) = &watcher else {
+                    return previewed;
+                };
+                if let Err(e) = previewed {
+                    error!("{e:#}");
+                    watcher.wait_config()?;
+                }
+            }
+        }
+        Command::Reveal {
+            config,
+            set,
+            output,
+            metadata,
+            revealed,
+            offset,
+            random_offset,
+            source,
+            ..
+        } => {
+            let config = config::load(source, config, set)?;
+            reveal::reveal(
+                source,
+                output,
+                metadata,
+                revealed,
+                &config,
+                *offset,
+                *random_offset,
+            )
+        }
     }
 }
 
@@ -86,6 +273,22 @@ enum Command {
         #[structopt(long = "metadata", default_value = "metadata")]
         metadata: String,
 
+        /// The number of times a failed video generation is retried before giving up on the token.
+        #[structopt(long = "retries", default_value = "1")]
+        retries: u32,
+
+        /// How to handle a token which could not be generated: 'abort' stops generation, 'skip' removes the token's partial output and continues.
+        #[structopt(long = "on-failure", default_value = "abort", possible_values = &["abort", "skip"])]
+        on_failure: FailurePolicy,
+
+        /// Keeps watching the source directory, re-rendering the tokens affected by changed files and regenerating the collection once the configuration changes.
+        #[structopt(long = "watch")]
+        watch: bool,
+
+        /// Overrides a configuration field (e.g. `supply=100` or `collection.name=Test`), where values are parsed as JSON if valid and otherwise as strings. Can be specified multiple times.
+        #[structopt(long = "set", number_of_values = 1)]
+        set: Vec<Override>,
+
         /// The logging verbosity: use multiple `v`s to increase verbosity.
         #[structopt(short = "v", long = "verbose", default_value = "1")]
         verbosity: u64,
@@ -112,6 +315,250 @@ enum Command {
         #[structopt(short = "v", long = "verbose", default_value = "1")]
         verbosity: u64,
 
+        /// The source directory, containing the required config.json configuration file.
+        #[structopt(parse(from_os_str))]
+        source: PathBuf,
+    },
+    /// Tiles the generated token media into one or more sheet images, optionally filtered by trait.
+    Mosaic {
+        /// The output directory name.
+        #[structopt(long = "output", short = "o", default_value = "output")]
+        output: String,
+
+        /// The output directory name of the token media.
+        #[structopt(long = "media", default_value = "media")]
+        media: String,
+
+        /// The output directory name of the token metadata.
+        #[structopt(long = "metadata", default_value = "metadata")]
+        metadata: String,
+
+        /// The output directory name for the resulting sheet images.
+        #[structopt(long = "mosaic", default_value = "mosaic")]
+        mosaic: String,
+
+        /// Only tiles tokens with the attribute value (e.g. `Hat=Crown`). Can be specified multiple times, where tokens must match a value of each attribute.
+        #[structopt(long = "filter", number_of_values = 1)]
+        filter: Vec<Selection>,
+
+        /// The number of tokens in each row.
+        #[structopt(long = "columns", default_value = "10")]
+        columns: u32,
+
+        /// The number of rows of each sheet, otherwise all tokens are tiled within a single sheet.
+        #[structopt(long = "rows")]
+        rows: Option<u32>,
+
+        /// The width and height of each token, in pixels.
+        #[structopt(long = "tile-size", default_value = "256")]
+        tile_size: u32,
+
+        /// The space around each token, in pixels.
+        #[structopt(long = "padding", default_value = "8")]
+        padding: u32,
+
+        /// Labels each token with its token number.
+        #[structopt(long = "labels")]
+        labels: bool,
+
+        /// The font file of the labels, defaulting to a sans-serif system font.
+        #[structopt(long = "font", parse(from_os_str))]
+        font: Option<PathBuf>,
+
+        /// The logging verbosity: use multiple `v`s to increase verbosity.
+        #[structopt(short = "v", long = "verbose", default_value = "1")]
+        verbosity: u64,
+
+        /// The source directory, containing the required config.json configuration file.
+        #[structopt(parse(from_os_str))]
+        source: PathBuf,
+    },
+    /// Renders a number of randomly generated tokens into a temporary directory, along with a contact sheet of their images and traits.
+    Preview {
+        /// The configuration file name.
+        #[structopt(long = "config", short = "c", default_value = "config.json")]
+        config: String,
+
+        /// Overrides a configuration field (e.g. `supply=100` or `collection.name=Test`), where values are parsed as JSON if valid and otherwise as strings. Can be specified multiple times.
+        #[structopt(long = "set", number_of_values = 1)]
+        set: Vec<Override>,
+
+        /// The number of tokens to render.
+        #[structopt(long = "count", default_value = "16")]
+        count: usize,
+
+        /// The number of tokens in each row of the contact sheet, defaulting to a square grid.
+        #[structopt(long = "columns")]
+        columns: Option<u32>,
+
+        /// The width and height of each token within the contact sheet, in pixels.
+        #[structopt(long = "tile-size", default_value = "256")]
+        tile_size: u32,
+
+        /// The font file of the captions, defaulting to a sans-serif system font.
+        #[structopt(long = "font", parse(from_os_str))]
+        font: Option<PathBuf>,
+
+        /// Keeps watching the source directory, re-rendering the tokens affected by changed files and sampling again once the configuration changes.
+        #[structopt(long = "watch")]
+        watch: bool,
+
+        /// The logging verbosity: use multiple `v`s to increase verbosity.
+        #[structopt(short = "v", long = "verbose", default_value = "1")]
+        verbosity: u64,
+
+        /// The source directory, containing the required config.json configuration file.
+        #[structopt(parse(from_os_str))]
+        source: PathBuf,
+    },
+    /// Reveals the generated metadata in place of the hidden metadata, optionally behind an offset.
+    Reveal {
+        /// The configuration file name.
+        #[structopt(long = "config", short = "c", default_value = "config.json")]
+        config: String,
+
+        /// The output directory name.
+        #[structopt(long = "output", short = "o", default_value = "output")]
+        output: String,
+
+        /// The output directory name of the generated token metadata.
+        #[structopt(long = "metadata", default_value = "metadata")]
+        metadata: String,
+
+        /// The output directory name for the revealed token metadata.
+        #[structopt(long = "revealed", default_value = "revealed")]
+        revealed: String,
+
+        /// The offset applied to the generated token number when revealing each token.
+        #[structopt(long = "offset", conflicts_with = "random-offset")]
+        offset: Option<usize>,
+
+        /// Reveal behind a randomly drawn offset, decided at the time of reveal.
+        #[structopt(long = "random-offset")]
+        random_offset: bool,
+
+        /// Overrides a configuration field (e.g. `supply=100` or `collection.name=Test`), where values are parsed as JSON if valid and otherwise as strings. Can be specified multiple times.
+        #[structopt(long = "set", number_of_values = 1)]
+        set: Vec<Override>,
+
+        /// The logging verbosity: use multiple `v`s to increase verbosity.
+        #[structopt(short = "v", long = "verbose", default_value = "1")]
+        verbosity: u64,
+
+        /// The source directory, containing the required config.json configuration file.
+        #[structopt(parse(from_os_str))]
+        source: PathBuf,
+    },
+    /// Serves the output directory over HTTP, along with a gallery of the generated tokens.
+    Serve {
+        /// The configuration file name.
+        #[structopt(long = "config", short = "c", default_value = "config.json")]
+        config: String,
+
+        /// Overrides a configuration field (e.g. `supply=100` or `collection.name=Test`), where values are parsed as JSON if valid and otherwise as strings. Can be specified multiple times.
+        #[structopt(long = "set", number_of_values = 1)]
+        set: Vec<Override>,
+
+        /// The output directory name.
+        #[structopt(long = "output", short = "o", default_value = "output")]
+        output: String,
+
+        /// The output directory name of the token media.
+        #[structopt(long = "media", default_value = "media")]
+        media: String,
+
+        /// The output directory name of the token metadata.
+        #[structopt(long = "metadata", default_value = "metadata")]
+        metadata: String,
+
+        /// The address to serve on.
+        #[structopt(long = "address", default_value = "127.0.0.1")]
+        address: String,
+
+        /// The port to serve on.
+        #[structopt(long = "port", short = "p", default_value = "8787")]
+        port: u16,
+
+        /// The logging verbosity: use multiple `v`s to increase verbosity.
+        #[structopt(short = "v", long = "verbose", default_value = "1")]
+        verbosity: u64,
+
+        /// The source directory, containing the required config.json configuration file.
+        #[structopt(parse(from_os_str))]
+        source: PathBuf,
+    },
+    /// Renders each attribute option above a neutral base, writing a sheet per attribute labelled with option weights and percentages.
+    TraitSheets {
+        /// The configuration file name.
+        #[structopt(long = "config", short = "c", default_value = "config.json")]
+        config: String,
+
+        /// Overrides a configuration field (e.g. `supply=100` or `collection.name=Test`), where values are parsed as JSON if valid and otherwise as strings. Can be specified multiple times.
+        #[structopt(long = "set", number_of_values = 1)]
+        set: Vec<Override>,
+
+        /// The output directory name.
+        #[structopt(long = "output", short = "o", default_value = "output")]
+        output: String,
+
+        /// The output directory name of the token media.
+        #[structopt(long = "media", default_value = "media")]
+        media: String,
+
+        /// The output directory name of the token metadata.
+        #[structopt(long = "metadata", default_value = "metadata")]
+        metadata: String,
+
+        /// The output directory name for the resulting trait sheets.
+        #[structopt(long = "traits", default_value = "traits")]
+        traits: String,
+
+        /// An option rendered beneath the options of other attributes (e.g. `Background=Grey`). Can be specified multiple times, where attributes without a base option are not rendered.
+        #[structopt(long = "base", number_of_values = 1)]
+        base: Vec<Selection>,
+
+        /// The number of options in each row.
+        #[structopt(long = "columns", default_value = "6")]
+        columns: u32,
+
+        /// The width and height of each option, in pixels.
+        #[structopt(long = "tile-size", default_value = "256")]
+        tile_size: u32,
+
+        /// The font file of the labels, defaulting to a sans-serif system font.
+        #[structopt(long = "font", parse(from_os_str))]
+        font: Option<PathBuf>,
+
+        /// The logging verbosity: use multiple `v`s to increase verbosity.
+        #[structopt(short = "v", long = "verbose", default_value = "1")]
+        verbosity: u64,
+
+        /// The source directory, containing the required config.json configuration file.
+        #[structopt(parse(from_os_str))]
+        source: PathBuf,
+    },
+    /// Verifies the media of each token against the provenance record created during generation.
+    VerifyProvenance {
+        /// The configuration file name.
+        #[structopt(long = "config", short = "c", default_value = "config.json")]
+        config: String,
+
+        /// The output directory name.
+        #[structopt(long = "output", short = "o", default_value = "output")]
+        output: String,
+
+        /// The output directory name of the token media.
+        #[structopt(long = "media", default_value = "media")]
+        media: String,
+
+        /// Overrides a configuration field (e.g. `supply=100` or `collection.name=Test`), where values are parsed as JSON if valid and otherwise as strings. Can be specified multiple times.
+        #[structopt(long = "set", number_of_values = 1)]
+        set: Vec<Override>,
+
+        /// The logging verbosity: use multiple `v`s to increase verbosity.
+        #[structopt(short = "v", long = "verbose", default_value = "1")]
+        verbosity: u64,
+
         /// The source directory, containing the required config.json configuration file.
         #[structopt(parse(from_os_str))]
         source: PathBuf,
@@ -123,6 +570,12 @@ impl Command {
         match self {
             Command::Generate { verbosity, .. } => *verbosity,
             Command::Deploy { verbosity, .. } => *verbosity,
+            Command::Mosaic { verbosity, .. } => *verbosity,
+            Command::Preview { verbosity, .. } => *verbosity,
+            Command::Reveal { verbosity, .. } => *verbosity,
+            Command::Serve { verbosity, .. } => *verbosity,
+            Command::TraitSheets { verbosity, .. } => *verbosity,
+            Command::VerifyProvenance { verbosity, .. } => *verbosity,
         }
     }
 }
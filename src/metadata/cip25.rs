@@ -0,0 +1,101 @@
+use indexmap::IndexMap;
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+
+// The transaction metadata label of CIP-25 token metadata
+pub const LABEL: &str = "721";
+// The maximum length of a string within Cardano transaction metadata
+const MAX_STRING_LENGTH: usize = 64;
+
+/// The combined CIP-25 metadata for a collection, serialized as a `721` map keyed by policy id
+/// and then asset name.
+pub struct Metadata<'a> {
+    pub policy_id: &'a str,
+    pub version: &'a str,
+    pub assets: &'a IndexMap<String, Value>,
+}
+
+impl Serialize for Metadata<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        struct Policies<'a>(&'a Metadata<'a>);
+
+        impl Serialize for Policies<'_> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry(self.0.policy_id, self.0.assets)?;
+                map.serialize_entry("version", self.0.version)?;
+                map.end()
+            }
+        }
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(LABEL, &Policies(self))?;
+        map.end()
+    }
+}
+
+/// A single asset within CIP-25 metadata.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Asset {
+    // The name of the asset.
+    pub name: String,
+    // A URI to the image of the asset, split into chunks when longer than 64 characters.
+    pub image: Value,
+    // The mime type of the image.
+    pub media_type: String,
+    // A description of the asset, split into chunks when longer than 64 characters.
+    pub description: Value,
+    // The files of the asset, including the image and any video.
+    pub files: Vec<File>,
+    // The attributes of the asset, keyed by name.
+    pub attributes: Map<String, Value>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct File {
+    pub name: String,
+    pub media_type: String,
+    pub src: Value,
+}
+
+/// Splits a string into chunks of at most 64 bytes, as required for Cardano transaction metadata.
+pub(crate) fn chunk(value: &str) -> Value {
+    if value.len() <= MAX_STRING_LENGTH {
+        return Value::from(value);
+    }
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for c in value.chars() {
+        if current.len() + c.len_utf8() > MAX_STRING_LENGTH {
+            chunks.push(Value::from(std::mem::take(&mut current)));
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        chunks.push(Value::from(current));
+    }
+    Value::Array(chunks)
+}
+
+/// Joins a string which may have been split into chunks.
+pub(crate) fn unchunk(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Array(chunks) => chunks
+            .iter()
+            .map(|c| c.as_str())
+            .collect::<Option<Vec<&str>>>()
+            .map(|c| c.concat()),
+        _ => None,
+    }
+}
@@ -0,0 +1,25 @@
+use serde::Serialize;
+
+/// Collection-level metadata, as referenced by the contractURI of a contract.
+#[derive(Serialize)]
+pub struct Metadata<'a> {
+    // Name of the collection.
+    pub name: &'a str,
+    // A human readable description of the collection. Markdown is supported.
+    pub description: &'a str,
+    // A URL to the image of the collection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    // A URL to the banner image of the collection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub banner_image: Option<String>,
+    // A URL to the site of the collection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_link: Option<&'a str>,
+    // The royalties for secondary sales, in basis points (i.e. 100 is 1%).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seller_fee_basis_points: Option<u16>,
+    // The address to which royalties are paid.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_recipient: Option<&'a str>,
+}
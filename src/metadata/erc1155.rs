@@ -0,0 +1,30 @@
+use super::Attribute;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Token metadata following the ERC-1155 metadata URI JSON schema.
+#[derive(Serialize)]
+pub struct Metadata<'a> {
+    // Identifies the asset to which this token represents.
+    pub name: String,
+    // The number of decimal places that the token amount should display.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+    // Describes the asset to which this token represents.
+    pub description: &'a str,
+    // A URI pointing to a resource with mime type image/* representing the asset to which this token represents.
+    pub image: String,
+    // A URL to a multi-media attachment for the item.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animation_url: Option<String>,
+    // This is the URL that will allow users to view the item on your site.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    // Background color of the item. Must be a six-character hexadecimal without a pre-pended #.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    // The attributes for the item, as understood by marketplaces.
+    pub attributes: Vec<Attribute<'a>>,
+    // Arbitrary properties.
+    pub properties: &'a Map<String, Value>,
+}
@@ -0,0 +1,12 @@
+use serde::Serialize;
+
+/// Placeholder metadata for a token prior to reveal, sharing a single image across all tokens.
+#[derive(Serialize)]
+pub struct Metadata<'a> {
+    // Name of the item.
+    pub name: String,
+    // A human readable description of the item. Markdown is supported.
+    pub description: &'a str,
+    // A URL to the placeholder image.
+    pub image: &'a str,
+}
@@ -0,0 +1,60 @@
+use super::Attribute;
+use serde::Serialize;
+
+/// Token metadata following the Metaplex token metadata standard, as expected by Candy Machine.
+#[derive(Serialize)]
+pub struct Metadata<'a> {
+    // Name of the asset.
+    pub name: String,
+    // Symbol of the asset.
+    pub symbol: &'a str,
+    // Description of the asset.
+    pub description: &'a str,
+    // Royalties percentage awarded to creators, in basis points (i.e. 500 is 5%).
+    pub seller_fee_basis_points: u16,
+    // URI pointing to the asset's image, relative to the metadata until deployed.
+    pub image: String,
+    // URI pointing to the asset's animation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animation_url: Option<String>,
+    // URI pointing to an external URL defining the asset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    // Array of attributes defining the characteristics of the asset.
+    pub attributes: Vec<Attribute<'a>>,
+    // Additional properties that define the asset.
+    pub properties: Properties<'a>,
+    // The collection the asset belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collection: Option<Collection<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct Properties<'a> {
+    // Additional files to include with the asset.
+    pub files: Vec<File>,
+    // The type of asset, i.e. image or video.
+    pub category: &'static str,
+    // The creators of the asset and their share of royalties.
+    pub creators: Vec<Creator<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct File {
+    pub uri: String,
+    #[serde(rename = "type")]
+    pub mime_type: String,
+}
+
+#[derive(Serialize)]
+pub struct Creator<'a> {
+    pub address: &'a str,
+    pub share: u8,
+}
+
+#[derive(Serialize)]
+pub struct Collection<'a> {
+    pub name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family: Option<&'a str>,
+}
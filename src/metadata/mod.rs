@@ -0,0 +1,292 @@
+pub mod cip25;
+pub mod collection;
+pub mod erc1155;
+pub mod hidden;
+pub mod metaplex;
+pub mod tzip21;
+
+use crate::config::{CustomMetadata, Standard};
+use anyhow::{Context, Result};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The file name of the collection-level metadata, written alongside the metadata directory.
+pub(crate) const COLLECTION_FILE_NAME: &str = "collection.json";
+
+/// The directory name of the placeholder metadata, written alongside the metadata directory.
+pub(crate) const HIDDEN_DIRECTORY: &str = "hidden";
+
+/// The file name of the combined CIP-25 metadata.
+pub(crate) const CIP25_FILE_NAME: &str = "cip25.json";
+
+// The name of the field holding the dna of the token, and of the field holding arbitrary properties
+// within the standards which define one
+const DNA_FIELD: &str = "dna";
+const PROPERTIES_FIELD: &str = "properties";
+
+/// The file name of the metadata for a token, as expected by the metadata standard.
+pub(crate) fn file_name(standard: Standard, token: usize) -> String {
+    match standard {
+        Standard::OpenSea => token.to_string(),
+        Standard::Metaplex => format!("{token}.json"),
+        // Clients substitute {id} with the lowercase hex token id, zero-padded to 64 characters
+        Standard::Erc1155 => format!("{token:064x}"),
+        Standard::Tzip21 => token.to_string(),
+        // Written as a single combined file once generation completes
+        Standard::Cip25 => CIP25_FILE_NAME.to_string(),
+    }
+}
+
+/// The mime type of a media file, based on its extension.
+pub(crate) fn mime_type(path: &Path) -> String {
+    mime_guess::from_path(path)
+        .first_or_octet_stream()
+        .essence_str()
+        .to_string()
+}
+
+/// Adds any DNA of the token, then applies the configured custom fields, renaming and field order
+/// to token metadata. Templated values are split into chunks for standards which limit string
+/// length (i.e. CIP-25).
+pub(crate) fn customize(
+    metadata: Value,
+    dna: &str,
+    custom: &CustomMetadata,
+    variables: &HashMap<String, String>,
+    standard: Standard,
+) -> Result<Value> {
+    let Value::Object(mut fields) = metadata else {
+        return Ok(metadata);
+    };
+
+    // The dna is only added where the standard allows additional fields
+    if custom.dna {
+        match standard {
+            Standard::OpenSea | Standard::Tzip21 => {
+                fields.insert(DNA_FIELD.to_string(), dna.into());
+            }
+            Standard::Erc1155 | Standard::Metaplex => {
+                if let Some(Value::Object(properties)) = fields.get_mut(PROPERTIES_FIELD) {
+                    properties.insert(DNA_FIELD.to_string(), dna.into());
+                }
+            }
+            Standard::Cip25 => {}
+        }
+    }
+    let chunked = standard == Standard::Cip25;
+
+    // Add custom fields, templating string values
+    for (name, value) in &custom.fields {
+        if value.is_null() {
+            fields.shift_remove(name);
+            continue;
+        }
+        let value = template(value, variables, chunked)
+            .with_context(|| format!("unable to template the custom metadata field '{name}'"))?;
+        fields.insert(name.clone(), value);
+    }
+
+    // Rename fields, retaining their position
+    if !custom.rename.is_empty() {
+        fields = fields
+            .into_iter()
+            .map(|(name, value)| match custom.rename.get(&name) {
+                Some(rename) => (rename.clone(), value),
+                None => (name, value),
+            })
+            .collect();
+    }
+
+    // Order fields, with any remaining fields following in their existing order
+    if !custom.order.is_empty() {
+        let mut ordered = Map::with_capacity(fields.len());
+        for name in &custom.order {
+            if let Some(value) = fields.shift_remove(name) {
+                ordered.insert(name.clone(), value);
+            }
+        }
+        ordered.extend(fields);
+        fields = ordered;
+    }
+
+    Ok(Value::Object(fields))
+}
+
+fn template(value: &Value, variables: &HashMap<String, String>, chunked: bool) -> Result<Value> {
+    Ok(match value {
+        Value::String(value) => {
+            let value = strfmt::strfmt(value, variables)?;
+            if chunked {
+                cip25::chunk(&value)
+            } else {
+                Value::String(value)
+            }
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|v| template(v, variables, chunked))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), template(v, variables, chunked)?)))
+                .collect::<Result<_>>()?,
+        ),
+        _ => value.clone(),
+    })
+}
+
+#[derive(Serialize)]
+pub struct Metadata<'a> {
+    // A URL to a multi-media attachment for the item. The file extensions GLTF, GLB, WEBM, MP4, M4V, OGV, and OGG are supported, along with the audio-only extensions MP3, WAV, and OGA.
+    // Animation_url also supports HTML pages, allowing you to build rich experiences and interactive NFTs using JavaScript canvas, WebGL, and more. Scripts and relative paths within the HTML page are now supported. However, access to browser extensions is not supported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animation_url: Option<String>,
+    // These are the attributes for the item, which will show up on the OpenSea page for the item. (see below)
+    pub attributes: Vec<Attribute<'a>>,
+    // Background color of the item on OpenSea. Must be a six-character hexadecimal without a pre-pended #.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    // A human readable description of the item. Markdown is supported.
+    pub description: &'a str,
+    // This is the URL that will appear below the asset's image on OpenSea and will allow users to leave OpenSea and view the item on your site.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    pub id: usize,
+    /// This is the URL to the image of the item. Can be just about any type of image (including SVGs, which will be cached into PNGs by OpenSea), and can be IPFS URLs or paths. We recommend using a 350 x 350 image.
+    pub image: String,
+    // Name of the item.
+    pub name: String,
+    // A URL to a YouTube video.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub youtube_url: Option<String>,
+}
+
+#[allow(dead_code)]
+pub enum Attribute<'a> {
+    String {
+        trait_type: &'a str,
+        value: Cow<'a, str>,
+    },
+    // Numeric
+    Number {
+        trait_type: &'a str,
+        value: usize,
+        max_value: Option<usize>,
+    },
+    BoostPercentage {
+        trait_type: &'a str,
+        value: f32,
+        max_value: Option<usize>,
+    },
+    BoostNumber {
+        trait_type: &'a str,
+        value: f32,
+        max_value: Option<usize>,
+    },
+    // Date
+    Date {
+        trait_type: &'a str,
+        // A unix timestamp (seconds)
+        value: i64,
+    },
+    // An attribute without any specific type
+    Value(&'static str, String),
+}
+
+impl Attribute<'_> {
+    /// The name and value of the attribute, for standards which model attributes as simple pairs.
+    pub(crate) fn pair(&self) -> (&str, serde_json::Value) {
+        match self {
+            Attribute::String { trait_type, value } => (trait_type, value.as_ref().into()),
+            Attribute::Number {
+                trait_type, value, ..
+            } => (trait_type, (*value).into()),
+            Attribute::BoostPercentage {
+                trait_type, value, ..
+            }
+            | Attribute::BoostNumber {
+                trait_type, value, ..
+            } => (trait_type, (*value).into()),
+            Attribute::Date { trait_type, value } => (trait_type, (*value).into()),
+            Attribute::Value(property, value) => (property, value.as_str().into()),
+        }
+    }
+}
+
+impl Serialize for Attribute<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        const ATTRIBUTE_NAME: &str = "Attribute";
+        match self {
+            Attribute::String { trait_type, value } => {
+                let mut state = serializer.serialize_struct(ATTRIBUTE_NAME, 2)?;
+                state.serialize_field("trait_type", trait_type)?;
+                state.serialize_field("value", value)?;
+                state.end()
+            }
+            Attribute::Number {
+                trait_type,
+                value,
+                max_value,
+            } => {
+                let mut state = serializer.serialize_struct(ATTRIBUTE_NAME, 4)?;
+                state.serialize_field("display_type", "number")?;
+                state.serialize_field("trait_type", trait_type)?;
+                state.serialize_field("value", value)?;
+                if let Some(max_value) = max_value {
+                    state.serialize_field("max_value", max_value)?;
+                }
+                state.end()
+            }
+            Attribute::BoostPercentage {
+                trait_type,
+                value,
+                max_value,
+            } => {
+                let mut state = serializer.serialize_struct(ATTRIBUTE_NAME, 4)?;
+                state.serialize_field("display_type", "boost_percentage")?;
+                state.serialize_field("trait_type", trait_type)?;
+                state.serialize_field("value", value)?;
+                if let Some(max_value) = max_value {
+                    state.serialize_field("max_value", max_value)?;
+                }
+                state.end()
+            }
+            Attribute::BoostNumber {
+                trait_type,
+                value,
+                max_value,
+            } => {
+                let mut state = serializer.serialize_struct(ATTRIBUTE_NAME, 4)?;
+                state.serialize_field("display_type", "boost_number")?;
+                state.serialize_field("trait_type", trait_type)?;
+                state.serialize_field("value", value)?;
+                if let Some(max_value) = max_value {
+                    state.serialize_field("max_value", max_value)?;
+                }
+                state.end()
+            }
+            Attribute::Date { trait_type, value } => {
+                let mut state = serializer.serialize_struct(ATTRIBUTE_NAME, 3)?;
+                state.serialize_field("display_type", "date")?;
+                state.serialize_field("trait_type", trait_type)?;
+                state.serialize_field("value", value)?;
+                state.end()
+            }
+            Attribute::Value(property, value) => {
+                let mut state = serializer.serialize_struct(ATTRIBUTE_NAME, 1)?;
+                state.serialize_field(property, value)?;
+                state.end()
+            }
+        }
+    }
+}
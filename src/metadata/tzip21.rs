@@ -0,0 +1,52 @@
+use serde::Serialize;
+
+/// Token metadata following the Tezos TZIP-21 rich metadata standard (e.g. objkt).
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metadata<'a> {
+    // Name of the token.
+    pub name: String,
+    // A short symbol for the token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<&'a str>,
+    // General notes, abstracts or summaries about the token.
+    pub description: &'a str,
+    // The number of decimal places the token amount is displayed with, which is zero for NFTs.
+    pub decimals: u8,
+    // Whether the token amount is a boolean, i.e. a single edition.
+    pub is_boolean_amount: bool,
+    // A URI to the asset itself, i.e. the video when available, otherwise the image.
+    pub artifact_uri: String,
+    // A URI to an image of the asset, used for display purposes.
+    pub display_uri: String,
+    // A URI to an image of the asset for wallets and client applications to have a scaled down image to present to end-users.
+    pub thumbnail_uri: String,
+    // A URI with additional information about the token.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_uri: Option<String>,
+    // The formats of the token's media.
+    pub formats: Vec<Format>,
+    // The primary person, people, or organization(s) responsible for creating the intellectual content of the asset.
+    pub creators: &'a [String],
+    // A statement about the asset rights.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rights: Option<&'a str>,
+    // A list of tags that describe the subject or content of the asset.
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub tags: &'a [String],
+    // Custom attributes about the subject or content of the asset.
+    pub attributes: Vec<Attribute<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Format {
+    pub uri: String,
+    pub mime_type: String,
+}
+
+#[derive(Serialize)]
+pub struct Attribute<'a> {
+    pub name: &'a str,
+    pub value: String,
+}
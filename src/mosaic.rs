@@ -0,0 +1,140 @@
+use crate::config::Selection;
+use crate::generation::plan::{self, Planned};
+use crate::sheet::{self, Layout, Tile};
+use crate::{output, PATH_TO_STRING_MSG};
+use anyhow::{anyhow, Context, Result};
+use indexmap::IndexMap;
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+
+// The extension of the sheet images, each named by its sheet number
+const SHEET_EXTENSION: &str = "png";
+
+/// The layout of the mosaic sheets.
+pub(crate) struct Options<'a> {
+    pub(crate) columns: u32,
+    /// The number of rows of each sheet, otherwise all tokens are tiled within a single sheet.
+    pub(crate) rows: Option<u32>,
+    pub(crate) tile_size: u32,
+    pub(crate) padding: u32,
+    /// Whether tokens are labelled with their token number.
+    pub(crate) labels: bool,
+    /// The font of labels, defaulting to a sans-serif system font.
+    pub(crate) font: Option<&'a Path>,
+}
+
+/// Tiles the media of the generated tokens matching all filters into one or more sheet images,
+/// written to the mosaic directory. Filters of the same attribute match any of their values.
+pub(crate) fn mosaic(
+    source: &Path,
+    output: &str,
+    media: &str,
+    metadata: &str,
+    mosaic: &str,
+    filters: &[Selection],
+    options: Options,
+) -> Result<()> {
+    output::check_editions(source, output)?;
+    let output = source.join(output);
+    let mosaic = output::init_sheets(&output, mosaic, media, metadata)?;
+    let media = output.join(media);
+    let mut values: IndexMap<&str, Vec<&str>> = IndexMap::new();
+    for filter in filters {
+        values
+            .entry(filter.attribute.as_str())
+            .or_default()
+            .push(filter.value.as_str());
+    }
+    let planned = plan::load(&output)?;
+    if let Some(attribute) = values.keys().find(|attribute| {
+        planned
+            .first()
+            .is_some_and(|p| !p.attributes.contains_key(**attribute))
+    }) {
+        return Err(anyhow!(
+            "unable to filter by unknown attribute '{attribute}'"
+        ));
+    }
+
+    // Tile the image of each matching token, in token order
+    let tokens: Vec<(&Planned, PathBuf)> = planned
+        .iter()
+        .filter(|planned| {
+            values.iter().all(|(attribute, values)| {
+                planned
+                    .attributes
+                    .get(*attribute)
+                    .is_some_and(|value| values.contains(&value.as_str()))
+            })
+        })
+        .filter_map(|planned| match output::token_image(&media, planned.token) {
+            Some(image) => Some((planned, image)),
+            None => {
+                warn!("token {} has no media and was not tiled", planned.token);
+                None
+            }
+        })
+        .collect();
+    if tokens.is_empty() {
+        warn!("no tokens to tile");
+        return Ok(());
+    }
+    info!("tiling {} token(s)...", tokens.len());
+
+    // Only the sheets of any previous mosaic are removed, as fewer sheets may be written
+    let mosaic_path = mosaic.to_str().expect(PATH_TO_STRING_MSG);
+    debug!("clearing previous mosaic within '{mosaic_path}'...");
+    for entry in std::fs::read_dir(&mosaic)
+        .with_context(|| format!("could not read mosaic directory {mosaic_path}"))?
+    {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some(SHEET_EXTENSION)
+            && path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| stem.parse::<usize>().is_ok())
+        {
+            std::fs::remove_file(&path).with_context(|| {
+                format!(
+                    "could not remove previous mosaic {}",
+                    path.to_str().expect(PATH_TO_STRING_MSG)
+                )
+            })?;
+        }
+    }
+
+    let font = match options.labels {
+        true => Some(sheet::font(options.font)?),
+        false => None,
+    };
+    let layout = Layout {
+        title: None,
+        columns: options.columns,
+        tile_size: options.tile_size,
+        padding: options.padding,
+        font: font.as_ref(),
+    };
+    let per_sheet = match options.rows {
+        Some(rows) => (options.columns.max(1) * rows.max(1)) as usize,
+        None => tokens.len(),
+    };
+    for (i, tokens) in tokens.chunks(per_sheet).enumerate() {
+        let tiles = tokens
+            .iter()
+            .map(|(planned, image)| {
+                Ok(Tile {
+                    image: sheet::open(image, options.tile_size)?
+                        .thumbnail(options.tile_size, options.tile_size),
+                    caption: vec![format!("#{}", planned.token)],
+                })
+            })
+            .collect::<Result<Vec<Tile>>>()?;
+        let sheet_path = mosaic.join(format!("{}.{SHEET_EXTENSION}", i + 1));
+        sheet::save(&layout.render(&tiles), &sheet_path)?;
+        info!(
+            "mosaic saved as '{}'",
+            sheet_path.to_str().expect(PATH_TO_STRING_MSG)
+        );
+    }
+    Ok(())
+}
@@ -1,10 +1,14 @@
+use crate::generation::plan;
 use crate::PATH_TO_STRING_MSG;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use log::{debug, trace, warn};
-use std::io::Read;
-use std::path::{Path, PathBuf};
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
 
-pub(crate) fn init(source: &PathBuf, output: &str, media: &str, metadata: &str) -> Result<PathBuf> {
+// The extensions of token images within the media directory
+const IMAGE_EXTENSIONS: [&str; 2] = ["png", "svg"];
+
+pub(crate) fn init(source: &Path, output: &str, media: &str, metadata: &str) -> Result<PathBuf> {
     debug!("checking output directories...");
     let output_path = init_output(source, output)?;
     init_media(&output_path, media)?;
@@ -12,7 +16,31 @@ pub(crate) fn init(source: &PathBuf, output: &str, media: &str, metadata: &str)
     Ok(output_path)
 }
 
-fn init_media(output: &PathBuf, media: &str) -> Result<()> {
+/// Creates a new, uniquely named output directory within the temporary directory, so that
+/// concurrent runs cannot clobber each other or follow an existing path (e.g. a symlink).
+pub(crate) fn init_temp(prefix: &str, media: &str, metadata: &str) -> Result<PathBuf> {
+    let temp = std::env::temp_dir();
+    let output_path = loop {
+        let output_path = temp.join(format!("{prefix}-{:08x}", rand::random::<u32>()));
+        match std::fs::create_dir(&output_path) {
+            Ok(()) => break output_path,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "could not create output directory {}",
+                        output_path.to_str().expect(PATH_TO_STRING_MSG)
+                    )
+                })
+            }
+        }
+    };
+    init_media(&output_path, media)?;
+    init_metadata(&output_path, metadata)?;
+    Ok(output_path)
+}
+
+fn init_media(output: &Path, media: &str) -> Result<()> {
     let media_path = output
         .join(media)
         .into_os_string()
@@ -27,7 +55,7 @@ fn init_media(output: &PathBuf, media: &str) -> Result<()> {
     Ok(())
 }
 
-fn init_metadata(output: &PathBuf, metadata: &str) -> Result<()> {
+fn init_metadata(output: &Path, metadata: &str) -> Result<()> {
     let metadata_path = output
         .join(metadata)
         .into_os_string()
@@ -43,7 +71,7 @@ fn init_metadata(output: &PathBuf, metadata: &str) -> Result<()> {
     Ok(())
 }
 
-fn init_output(source: &PathBuf, output: &str) -> Result<PathBuf> {
+fn init_output(source: &Path, output: &str) -> Result<PathBuf> {
     let output = source.join(output);
     let output_path = &output.to_str().expect(PATH_TO_STRING_MSG);
     trace!("checking output directory '{output_path}' exists...");
@@ -51,8 +79,8 @@ fn init_output(source: &PathBuf, output: &str) -> Result<PathBuf> {
     if Path::new(&output).is_dir() {
         // Clear output as config may have changed
         warn!("output directory '{output_path}' already exists and needs to be cleared: press enter when ready to continue...");
-        let mut buffer: Vec<u8> = vec![];
-        std::io::stdin().read(&mut *buffer)?;
+        let mut buffer = String::new();
+        std::io::stdin().read_line(&mut buffer)?;
         std::fs::remove_dir_all(&output)
             .with_context(|| format!("could not create output directory {output_path}"))?;
     }
@@ -61,3 +89,73 @@ fn init_output(source: &PathBuf, output: &str) -> Result<PathBuf> {
         .with_context(|| format!("could not create output directory {output_path}"))?;
     Ok(output)
 }
+
+/// Fails if the output directory holds editions output separately, each as a collection within its
+/// own directory, rather than a single collection.
+pub(crate) fn check_editions(source: &Path, output: &str) -> Result<()> {
+    let output_path = source.join(output);
+    if plan::exists(&output_path) {
+        return Ok(());
+    }
+    let mut editions: Vec<String> = std::fs::read_dir(&output_path)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| plan::exists(&entry.path()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    editions.sort();
+    match editions.first() {
+        Some(edition) => Err(anyhow!(
+            "output directory '{output}' holds editions output separately - run again for each edition with its own output directory (e.g. `--output \"{}\"`)",
+            Path::new(output)
+                .join(edition)
+                .to_str()
+                .expect(PATH_TO_STRING_MSG)
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Creates the directory of generated sheets within the output directory (if needed), failing
+/// unless it is named as a directory within the output directory other than the media or metadata
+/// directories, so that writing sheets cannot overwrite the collection or its source.
+pub(crate) fn init_sheets(
+    output: &Path,
+    sheets: &str,
+    media: &str,
+    metadata: &str,
+) -> Result<PathBuf> {
+    let name = Path::new(sheets);
+    if sheets.is_empty()
+        || !name
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(anyhow!(
+            "'{sheets}' is not a directory name within the output directory"
+        ));
+    }
+    if [media, metadata]
+        .into_iter()
+        .any(|directory| name.starts_with(directory))
+    {
+        return Err(anyhow!(
+            "'{sheets}' is the media or metadata directory of the collection"
+        ));
+    }
+
+    let sheets = output.join(name);
+    let sheets_path = sheets.to_str().expect(PATH_TO_STRING_MSG);
+    std::fs::create_dir_all(&sheets)
+        .with_context(|| format!("could not create sheet directory {sheets_path}"))?;
+    Ok(sheets)
+}
+
+/// The image of a token within the media directory, if any.
+pub(crate) fn token_image(media: &Path, token: usize) -> Option<PathBuf> {
+    IMAGE_EXTENSIONS
+        .into_iter()
+        .map(|extension| media.join(format!("{token}.{extension}")))
+        .find(|path| path.is_file())
+}
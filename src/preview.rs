@@ -0,0 +1,118 @@
+use crate::generation::Sample;
+use crate::sheet::{self, Layout, Tile};
+use crate::watch::{Change, Watcher};
+use crate::{generation, output, Config, PATH_TO_STRING_MSG};
+use anyhow::Result;
+use log::info;
+use rusttype::Font;
+use std::path::Path;
+
+// The prefix of the uniquely named preview directory, within the temporary directory
+const PREVIEW_DIRECTORY: &str = "ng-preview";
+// The directory names of the rendered media and metadata
+const MEDIA_DIRECTORY: &str = "media";
+const METADATA_DIRECTORY: &str = "metadata";
+// The file name of the contact sheet, written within the preview directory
+const SHEET_FILE_NAME: &str = "preview.png";
+
+/// Renders a number of randomly generated tokens within a temporary directory, writing a contact
+/// sheet of their images captioned with their traits. When watching, the tokens affected by changed
+/// files are re-rendered and the contact sheet updated, until the configuration changes.
+pub(crate) async fn preview(
+    source: &Path,
+    mut config: Config,
+    count: usize,
+    columns: Option<u32>,
+    tile_size: u32,
+    font: Option<&Path>,
+    watcher: Option<&Watcher>,
+) -> Result<()> {
+    let font = sheet::font(font)?;
+
+    // Preview within a temporary directory, leaving any generated output untouched
+    let preview = output::init_temp(PREVIEW_DIRECTORY, MEDIA_DIRECTORY, METADATA_DIRECTORY)?;
+    let preview_path = preview.to_str().expect(PATH_TO_STRING_MSG);
+    info!("previewing within '{preview_path}'");
+
+    info!("rendering {count} preview token(s)...");
+    config.supply = count;
+    let mut sampler = generation::Sampler::new(
+        source,
+        preview_path,
+        MEDIA_DIRECTORY,
+        METADATA_DIRECTORY,
+        &config,
+        tile_size,
+    )?;
+    let mut tokens = Vec::new();
+    let mut tiles = Vec::new();
+    for sample in sampler.render().await? {
+        tokens.push(sample.token);
+        tiles.push(tile(sample, tile_size)?);
+    }
+    save(&preview, &tiles, columns, tile_size, &font)?;
+
+    // Re-render the samples affected by changed files, until the configuration changes
+    let Some(watcher) = watcher else {
+        return Ok(());
+    };
+    while let Change::Files(changed) = watcher.wait()? {
+        let samples = sampler.update(&changed).await;
+        if samples.is_empty() {
+            continue;
+        }
+        for sample in samples {
+            let token = sample.token;
+            let tile = tile(sample, tile_size)?;
+            // Tiles remain in token order, including those of tokens previously without an image
+            let i = tokens.partition_point(|&t| t < token);
+            if tokens.get(i) == Some(&token) {
+                tiles[i] = tile;
+            } else {
+                tokens.insert(i, token);
+                tiles.insert(i, tile);
+            }
+        }
+        save(&preview, &tiles, columns, tile_size, &font)?;
+    }
+    Ok(())
+}
+
+/// The token image, fitted to the tile size and captioned with its traits.
+fn tile(sample: Sample, tile_size: u32) -> Result<Tile> {
+    let image = sheet::open(&sample.image, tile_size)?.thumbnail(tile_size, tile_size);
+    Ok(Tile {
+        image,
+        caption: std::iter::once(format!("#{}", sample.token))
+            .chain(
+                sample
+                    .attributes
+                    .iter()
+                    .map(|(trait_type, value)| format!("{trait_type}: {value}")),
+            )
+            .collect(),
+    })
+}
+
+fn save(
+    preview: &Path,
+    tiles: &[Tile],
+    columns: Option<u32>,
+    tile_size: u32,
+    font: &Font<'static>,
+) -> Result<()> {
+    let layout = Layout {
+        title: None,
+        columns: columns.unwrap_or_else(|| (tiles.len() as f64).sqrt().ceil() as u32),
+        tile_size,
+        padding: tile_size / 16,
+        font: Some(font),
+    };
+    let sheet_path = preview.join(SHEET_FILE_NAME);
+    sheet::save(&layout.render(tiles), &sheet_path)?;
+    info!(
+        "preview saved as '{}'",
+        sheet_path.to_str().expect(PATH_TO_STRING_MSG)
+    );
+    Ok(())
+}
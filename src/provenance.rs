@@ -0,0 +1,195 @@
+use crate::{output, PATH_TO_STRING_MSG};
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, trace, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+
+// The file name of the provenance record, written within the output directory
+const PROVENANCE_FILE_NAME: &str = "provenance.json";
+// A description of how the provenance hash is computed, recorded within the provenance record
+const PROVENANCE_SCHEME: &str = "SHA-256 of the concatenated lowercase hex SHA-256 hashes of each \
+    media file, in token order and, for each token, in the order of its media (the png or svg \
+    image, then any video)";
+
+/// The provenance record of a collection, proving the media of each token has not changed since
+/// generation.
+#[derive(Deserialize, Serialize)]
+pub(crate) struct Provenance {
+    // The SHA-256 hash of the concatenated media hashes of all tokens, in token order.
+    provenance: String,
+    // How the provenance hash is computed from the media hashes.
+    #[serde(default)]
+    scheme: String,
+    // The offset applied when revealing tokens, decided after the provenance is published.
+    starting_index: Option<usize>,
+    // The number of the first token.
+    start_token: usize,
+    // The total number of tokens.
+    supply: usize,
+    // The media hashes of each token.
+    tokens: Vec<Token>,
+}
+
+#[derive(Deserialize, PartialEq, Serialize)]
+struct Token {
+    token: usize,
+    media: Vec<Media>,
+}
+
+#[derive(Deserialize, PartialEq, Serialize)]
+struct Media {
+    // The file name of the media, within the media directory.
+    file: String,
+    // The SHA-256 hash of the media file.
+    hash: String,
+}
+
+impl Provenance {
+    /// Creates the provenance record from the media of the generated tokens.
+    pub(crate) fn new(
+        media: &Path,
+        tokens: &[usize],
+        start_token: usize,
+        supply: usize,
+        container: &str,
+    ) -> Result<Self> {
+        let tokens = tokens
+            .iter()
+            .map(|&token| hash_token(media, token, container))
+            .collect::<Result<Vec<Token>>>()?;
+        Ok(Self {
+            provenance: provenance_hash(&tokens),
+            scheme: PROVENANCE_SCHEME.to_string(),
+            starting_index: None,
+            start_token,
+            supply,
+            tokens,
+        })
+    }
+
+    /// Saves the provenance record within the output directory.
+    pub(crate) fn save(&self, output: &Path) -> Result<()> {
+        let provenance_path = output
+            .join(PROVENANCE_FILE_NAME)
+            .into_os_string()
+            .into_string()
+            .expect(PATH_TO_STRING_MSG);
+        debug!("saving provenance record as '{provenance_path}'");
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&provenance_path)?;
+        serde_json::to_writer_pretty(file, self)
+            .with_context(|| format!("error saving {provenance_path}"))
+    }
+
+    fn load(output: &Path) -> Result<Option<Self>> {
+        let provenance_path = output.join(PROVENANCE_FILE_NAME);
+        if !provenance_path.is_file() {
+            return Ok(None);
+        }
+        let provenance_path = provenance_path.to_str().expect(PATH_TO_STRING_MSG);
+        trace!("reading provenance record from '{provenance_path}'...");
+        let file = fs::File::open(provenance_path)
+            .with_context(|| format!("unable to read provenance record from {provenance_path}"))?;
+        serde_json::from_reader(file)
+            .with_context(|| format!("unable to read provenance record from {provenance_path}"))
+            .map(Some)
+    }
+}
+
+/// Records the starting index (i.e. the reveal offset) within any existing provenance record.
+pub(crate) fn record_starting_index(output: &Path, starting_index: usize) -> Result<()> {
+    if let Some(mut provenance) = Provenance::load(output)? {
+        provenance.starting_index = Some(starting_index);
+        provenance.save(output)?;
+    }
+    Ok(())
+}
+
+/// Recomputes the provenance record from the media within the output directory, failing if the
+/// media of any token or the resulting provenance hash differs from the saved record.
+pub(crate) fn verify(source: &Path, output: &str, media: &str, container: &str) -> Result<()> {
+    output::check_editions(source, output)?;
+    let output = source.join(output);
+    let provenance = Provenance::load(&output)?.ok_or_else(|| {
+        anyhow!(
+            "could not find '{}' - generate the collection and try again",
+            output
+                .join(PROVENANCE_FILE_NAME)
+                .to_str()
+                .expect(PATH_TO_STRING_MSG)
+        )
+    })?;
+
+    let media = output.join(media);
+    let mut tokens = Vec::with_capacity(provenance.tokens.len());
+    let mut mismatched = Vec::new();
+    for expected in &provenance.tokens {
+        let token = hash_token(&media, expected.token, container)?;
+        if &token != expected {
+            warn!("the media of token {} has changed", expected.token);
+            mismatched.push(expected.token);
+        }
+        tokens.push(token);
+    }
+
+    let hash = provenance_hash(&tokens);
+    if hash != provenance.provenance {
+        return Err(anyhow!(
+            "provenance hash of {hash} does not match the recorded provenance hash of {} - the media of {} token(s) has changed",
+            provenance.provenance,
+            mismatched.len()
+        ));
+    }
+
+    info!("provenance hash of {hash} verified");
+    if let Some(starting_index) = provenance.starting_index {
+        info!("tokens were revealed with a starting index of {starting_index}");
+    }
+    Ok(())
+}
+
+fn hash_token(media: &Path, token: usize, container: &str) -> Result<Token> {
+    let media = media_files(media, token, container)
+        .into_iter()
+        .map(|path| {
+            let bytes = fs::read(&path).with_context(|| {
+                format!(
+                    "unable to read media from {}",
+                    path.to_str().expect(PATH_TO_STRING_MSG)
+                )
+            })?;
+            Ok(Media {
+                file: path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .expect(PATH_TO_STRING_MSG)
+                    .to_string(),
+                hash: format!("{:x}", Sha256::digest(bytes)),
+            })
+        })
+        .collect::<Result<Vec<Media>>>()?;
+    Ok(Token { token, media })
+}
+
+fn provenance_hash(tokens: &[Token]) -> String {
+    let concatenated: String = tokens
+        .iter()
+        .flat_map(|t| t.media.iter().map(|m| m.hash.as_str()))
+        .collect();
+    format!("{:x}", Sha256::digest(concatenated))
+}
+
+/// The media files of a token within the media directory: its image and any video.
+pub(crate) fn media_files(media: &Path, token: usize, container: &str) -> Vec<PathBuf> {
+    ["png", "svg", container]
+        .into_iter()
+        .map(|extension| media.join(format!("{token}.{extension}")))
+        .filter(|path| path.is_file())
+        .collect()
+}
@@ -1,77 +1,133 @@
-use crate::config::{Attribute, AttributeOption};
+use crate::config::{Attribute, AttributeOption, Edition};
 use crate::Config;
 use anyhow::{Context, Result};
 use indexmap::IndexMap;
-use log::debug;
+use log::{debug, trace, warn};
 use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use thousands::Separable;
 
-pub(crate) type AttributeValue = str;
+pub(crate) type Token<'a> = Vec<(&'a Attribute, AttributeValue<'a>, &'a AttributeOption)>;
 
-pub(crate) fn generate(
-    config: &Config,
-) -> Result<Vec<Vec<(&Attribute, &AttributeValue, &AttributeOption)>>> {
+/// The chosen value of an attribute: the name of the chosen option, along with the number drawn
+/// for the token when the option is a range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct AttributeValue<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) number: Option<i64>,
+}
+
+impl<'a> From<&'a str> for AttributeValue<'a> {
+    /// The value of an option without any number drawn (e.g. when rendering the option alone).
+    fn from(name: &'a str) -> Self {
+        Self { name, number: None }
+    }
+}
+
+impl Display for AttributeValue<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+/// Randomly chooses the attribute values of each token, either of the whole collection or of a
+/// single edition using its weight overrides.
+pub(crate) fn generate<'a>(
+    config: &'a Config,
+    edition: Option<&Edition>,
+) -> Result<Vec<Token<'a>>> {
+    let (supply, start_token) = match edition {
+        Some(edition) => (edition.supply, edition.tokens().start),
+        None => (config.supply, config.start_token),
+    };
     debug!(
         "randomly generating {} items of each attribute, using the weights specified in config...",
-        config.supply.separate_with_commas(),
+        supply.separate_with_commas(),
     );
+    let weight = |attribute: &Attribute, value: &str, option: &AttributeOption| {
+        weight(edition, attribute, value, option)
+    };
 
     let mut rng = &mut rand::thread_rng();
-    let mut results: IndexMap<&Attribute, Vec<(&AttributeValue, &AttributeOption)>> =
+    let mut results: IndexMap<&Attribute, Vec<(AttributeValue, &AttributeOption)>> =
         IndexMap::with_capacity(config.attributes.len());
-    let mut stats: IndexMap<&str, IndexMap<&str, Stats>> = IndexMap::new();
-    for attribute in &config.attributes {
+
+    // Choose attributes in dependency order, so that conditional options can be filtered using the
+    // values already chosen for each token
+    for attribute in config.sampling_order()? {
         let options = &attribute.options;
-        let weighted_index = WeightedIndex::new(options.values().map(|option| option.weight()))
+        let generated: Vec<(AttributeValue, &AttributeOption)> = if attribute
+            .dependencies()
+            .is_empty()
+        {
+            let weighted_index = WeightedIndex::new(
+                options
+                    .iter()
+                    .map(|(value, option)| weight(attribute, value, option)),
+            )
             .with_context(|| {
                 format!(
                     "failed to generate the weighted index for the {} attribute",
                     attribute.name
                 )
             })?;
+            (0..supply)
+                .map(|_| option(attribute, weighted_index.sample(&mut rng), &mut rng))
+                .collect()
+        } else {
+            // Cache the weighted index of each distinct set of available options
+            let mut weighted_indices: HashMap<Vec<usize>, WeightedIndex<f64>> = HashMap::new();
+            let mut generated = Vec::with_capacity(supply);
+            for token in 0..supply {
+                let available: Vec<usize> = options
+                    .values()
+                    .enumerate()
+                    .filter(|(_, option)| {
+                        option.when().iter().all(|(name, values)| {
+                            let chosen = results
+                                .iter()
+                                .find(|(a, _)| &a.name == name)
+                                .map(|(_, chosen)| chosen[token].0.name)
+                                .expect("expected dependency to be chosen first");
+                            values.iter().any(|v| v == chosen)
+                        })
+                    })
+                    .map(|(i, _)| i)
+                    .collect();
+                if !weighted_indices.contains_key(&available) {
+                    let weighted_index = WeightedIndex::new(
+                        available
+                            .iter()
+                            .map(|&i| {
+                                let (value, option) = options.get_index(i).expect("expected option");
+                                weight(attribute, value, option)
+                            }),
+                    )
+                    .with_context(|| {
+                        format!(
+                            "no options of the {} attribute are available for the values chosen for token {}",
+                            attribute.name,
+                            token + start_token
+                        )
+                    })?;
+                    weighted_indices.insert(available.clone(), weighted_index);
+                }
+                let i = weighted_indices[&available].sample(&mut rng);
+                generated.push(option(attribute, available[i], &mut rng));
+            }
+            generated
+        };
 
-        let generated: Vec<(&AttributeValue, &AttributeOption)> = (0..config.supply)
-            .map(|_| {
-                let i = weighted_index.sample(&mut rng);
-                options
-                    .get_index(i)
-                    .map(|k| (k.0.as_ref(), k.1))
-                    .expect(&format!("failed to get the attribute value at index {i}"))
-            })
-            .collect();
-
-        let total_weight = attribute
-            .options
-            .values()
-            .map(|option| option.weight())
-            .sum();
-        let attribute_stats = attribute
-            .options
-            .iter()
-            .map(|(value, _)| {
-                (
-                    value.as_ref(),
-                    Stats {
-                        weight: *attribute.options[value].weight(),
-                        total_weight,
-                        count: 0,
-                        total_items: generated.len(),
-                    },
-                )
-            })
-            .collect();
-        stats.insert(
-            &attribute.name,
-            generated.iter().fold(attribute_stats, |mut f, value| {
-                f[value.0].count += 1;
-                f
-            }),
-        );
-
-        results.insert(&attribute, generated);
+        results.insert(attribute, generated);
     }
-    let results = (0..config.supply).fold(Vec::with_capacity(config.supply), |mut v, i| {
-        let attributes: Vec<(&Attribute, &AttributeValue, &AttributeOption)> = results
+    // Restore the configured attribute order
+    let position = |name: &str| config.attributes.iter().position(|a| a.name == name);
+    results.sort_by(|a, _, b, _| position(&a.name).cmp(&position(&b.name)));
+    let results = (0..supply).fold(Vec::with_capacity(supply), |mut v, i| {
+        let attributes: Token = results
             .iter()
             .map(|(attribute, options)| (*attribute, options[i].0, options[i].1))
             .collect();
@@ -80,7 +136,7 @@ pub(crate) fn generate(
     });
 
     debug!("generation complete, outputting attribute stats...");
-    for (attribute, mut stats) in stats.into_iter().rev() {
+    for (attribute, mut stats) in stats(config, edition, &results).into_iter().rev() {
         stats.sort_by(|k, _, k2, _| k.cmp(k2));
 
         debug!(
@@ -97,25 +153,195 @@ pub(crate) fn generate(
                 .join(", ")
         );
     }
-    // todo: include stats on duplicates
+
+    // Identify duplicate tokens by their dna, listing the tokens sharing each duplicated dna
+    let mut dnas: IndexMap<String, Vec<usize>> = IndexMap::with_capacity(results.len());
+    for (i, token) in results.iter().enumerate() {
+        let dna = dna(token);
+        trace!("token {} has dna {dna}", i + start_token);
+        dnas.entry(dna).or_default().push(i + start_token);
+    }
+    dnas.retain(|_, tokens| tokens.len() > 1);
+    if dnas.is_empty() {
+        debug!("no duplicate tokens generated");
+    } else {
+        warn!(
+            "{} duplicate token(s) generated across {} dna",
+            dnas.values()
+                .map(|tokens| tokens.len() - 1)
+                .sum::<usize>()
+                .separate_with_commas(),
+            dnas.len().separate_with_commas()
+        );
+        for (dna, tokens) in &dnas {
+            debug!(
+                "dna {dna} is shared by tokens {}",
+                tokens
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+        }
+    }
 
     Ok(results)
 }
 
+/// The expected and actual occurrence of each option of each attribute within the tokens, in
+/// attribute order.
+pub(crate) fn stats<'a>(
+    config: &'a Config,
+    edition: Option<&Edition>,
+    tokens: &[Token],
+) -> IndexMap<&'a str, IndexMap<&'a str, Stats>> {
+    config
+        .attributes
+        .iter()
+        .map(|attribute| {
+            let weights: Vec<f64> = attribute
+                .options
+                .iter()
+                .map(|(value, option)| weight(edition, attribute, value, option))
+                .collect();
+            let mut attribute_stats: IndexMap<&str, Stats> = attribute
+                .options
+                .keys()
+                .zip(&weights)
+                .map(|(value, _)| {
+                    (
+                        value.as_ref(),
+                        Stats {
+                            expected: 0.0,
+                            count: 0,
+                            total_items: tokens.len(),
+                        },
+                    )
+                })
+                .collect();
+            for token in tokens {
+                // Conditional options are only available given the values chosen for the token, so
+                // the expected share of each option is that among the options available to it
+                let available: Vec<bool> = attribute
+                    .options
+                    .values()
+                    .map(|option| {
+                        option.when().iter().all(|(name, values)| {
+                            token.iter().find(|(a, _, _)| &a.name == name).is_some_and(
+                                |(_, chosen, _)| values.iter().any(|v| v == chosen.name),
+                            )
+                        })
+                    })
+                    .collect();
+                let total_weight: f64 = weights
+                    .iter()
+                    .zip(&available)
+                    .filter(|(_, &available)| available)
+                    .map(|(weight, _)| weight)
+                    .sum();
+                if total_weight > 0.0 {
+                    for ((stats, weight), _) in attribute_stats
+                        .values_mut()
+                        .zip(&weights)
+                        .zip(&available)
+                        .filter(|(_, &available)| available)
+                    {
+                        stats.expected += weight / total_weight;
+                    }
+                }
+                if let Some((_, value, _)) = token.iter().find(|(a, _, _)| a.name == attribute.name)
+                {
+                    attribute_stats[value.name].count += 1;
+                }
+            }
+            (attribute.name.as_str(), attribute_stats)
+        })
+        .collect()
+}
+
+/// The weight of an option, as overridden by any edition.
+pub(crate) fn weight(
+    edition: Option<&Edition>,
+    attribute: &Attribute,
+    value: &str,
+    option: &AttributeOption,
+) -> f64 {
+    edition
+        .and_then(|e| e.weights.get(&attribute.name))
+        .and_then(|weights| weights.get(value))
+        .copied()
+        .unwrap_or(*option.weight())
+}
+
+/// The option at the index, drawing a number for the token when the option is a range.
+fn option<'a>(
+    attribute: &'a Attribute,
+    i: usize,
+    rng: &mut impl Rng,
+) -> (AttributeValue<'a>, &'a AttributeOption) {
+    let (name, option) = attribute
+        .options
+        .get_index(i)
+        .unwrap_or_else(|| panic!("failed to get the attribute value at index {i}"));
+    let number = match option {
+        AttributeOption::Range { min, max, .. } => Some(rng.gen_range(*min..=*max)),
+        _ => None,
+    };
+    (
+        AttributeValue {
+            name: name.as_ref(),
+            number,
+        },
+        option,
+    )
+}
+
+/// The DNA of a token: a SHA-256 hash of the chosen value of each attribute (including any number
+/// drawn from a range), in layer order from the bottom layer up (as ordered by any z-index).
+pub(crate) fn dna(token: &[(&Attribute, AttributeValue, &AttributeOption)]) -> String {
+    let mut layers: Vec<(usize, &(&Attribute, AttributeValue, &AttributeOption))> = token
+        .iter()
+        .enumerate()
+        .map(|(layer, attribute)| (attribute.0.z.unwrap_or(layer), attribute))
+        .collect();
+    layers.sort_by_key(|(z, _)| *z);
+
+    let mut hasher = Sha256::new();
+    for (_, (attribute, value, _)) in layers {
+        // Length-prefix names and values so that different combinations cannot collide
+        let number = value.number.map(|number| number.to_string());
+        for part in [attribute.name.as_str(), value.name]
+            .into_iter()
+            .chain(number.as_deref())
+        {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
 #[derive(Debug)]
-struct Stats {
-    weight: f64,
-    total_weight: f64,
+pub(crate) struct Stats {
+    // The sum of the expected share of the option across the tokens.
+    expected: f64,
     count: usize,
     total_items: usize,
 }
 
 impl Stats {
-    fn expected_weight_percentage(&self) -> f64 {
-        (self.weight / self.total_weight) * 100.0
+    /// Adds the occurrence of the option within other tokens (e.g. those of another edition).
+    pub(crate) fn add(&mut self, other: &Stats) {
+        self.expected += other.expected;
+        self.count += other.count;
+        self.total_items += other.total_items;
+    }
+
+    pub(crate) fn expected_weight_percentage(&self) -> f64 {
+        (self.expected / self.total_items as f64) * 100.0
     }
 
-    fn actual_percentage(&self) -> f64 {
+    pub(crate) fn actual_percentage(&self) -> f64 {
         (self.count as f64 / self.total_items as f64) * 100.0
     }
 }
@@ -0,0 +1,246 @@
+use crate::config::{EditionOutput, Standard};
+use crate::generation::plan::{self, Planned};
+use crate::{metadata, provenance};
+use crate::{Config, PATH_TO_STRING_MSG};
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info, trace, warn};
+use rand::Rng;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::fs::OpenOptions;
+use std::path::Path;
+
+// The names of the token number, dna and edition variables within token name formats
+const ID: &str = "id";
+const DNA: &str = "dna";
+const EDITION: &str = "edition";
+// The file name of the reveal record, written alongside the revealed metadata directory
+const REVEAL_FILE_NAME: &str = "reveal.json";
+
+/// The record of a reveal, allowing the mapping of tokens to generated metadata to be verified.
+#[derive(Serialize)]
+struct Reveal {
+    // The offset applied to the generated token number when revealing each token.
+    offset: usize,
+    // The number of the first token.
+    start_token: usize,
+    // The total number of tokens.
+    supply: usize,
+    // The number of each token, in token order, where the token at index `i` receives the metadata
+    // generated for the token at index `(i + offset) % supply`.
+    tokens: Vec<usize>,
+}
+
+/// Writes the real (generated) metadata of each token to the revealed directory, where the token at
+/// index `i` (in token order) receives the metadata generated for the token at index
+/// `(i + offset) % supply`.
+pub(crate) fn reveal(
+    source: &Path,
+    output: &str,
+    metadata: &str,
+    revealed: &str,
+    config: &Config,
+    offset: Option<usize>,
+    random_offset: bool,
+) -> Result<()> {
+    if config.standard == Standard::Cip25 {
+        return Err(anyhow!(
+            "reveal is not supported by the cip25 metadata standard"
+        ));
+    }
+
+    if !config.editions.is_empty() && config.edition_output == EditionOutput::Separate {
+        return Err(anyhow!(
+            "reveal is not supported for editions output separately - output editions continuously"
+        ));
+    }
+
+    // Tokens are revealed as planned, which may not be contiguous across editions
+    let plan: HashMap<usize, Planned> = plan::load(&source.join(output))?
+        .into_iter()
+        .map(|planned| (planned.token, planned))
+        .collect();
+    let mut tokens: Vec<usize> = plan.keys().copied().collect();
+    tokens.sort_unstable();
+
+    let supply = tokens.len();
+    if supply == 0 {
+        return Ok(());
+    }
+    let offset = match (offset, random_offset) {
+        (Some(offset), _) => offset % supply,
+        (None, true) => rand::thread_rng().gen_range(0..supply),
+        (None, false) => 0,
+    };
+    info!("revealing {supply} token(s) with an offset of {offset}...");
+
+    let metadata_path = source.join(output).join(metadata);
+    let revealed_path = source.join(output).join(revealed);
+    fs::create_dir_all(&revealed_path).with_context(|| {
+        format!(
+            "could not create revealed metadata output directory {}",
+            revealed_path.to_str().expect(PATH_TO_STRING_MSG)
+        )
+    })?;
+
+    // Fields are updated by their (possibly renamed) name within the generated metadata
+    let field = |name: &str| -> String {
+        config
+            .metadata
+            .rename
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    };
+    let id_field = field("id");
+    let attributes_field = field("attributes");
+    let name_field = field("name");
+    let external_url_field = field("external_url");
+
+    let mut missing = Vec::new();
+    for (i, &token) in tokens.iter().enumerate() {
+        let generated = tokens[(i + offset) % supply];
+        let path = metadata_path.join(metadata::file_name(config.standard, generated));
+        if !path.is_file() {
+            missing.push(token);
+            continue;
+        }
+
+        trace!(
+            "reading metadata from '{}'...",
+            path.to_str().expect(PATH_TO_STRING_MSG)
+        );
+        let file = fs::File::open(&path).with_context(|| {
+            format!(
+                "unable to read metadata from {}",
+                path.to_str().expect(PATH_TO_STRING_MSG)
+            )
+        })?;
+        let mut json: Value = serde_json::from_reader(file).with_context(|| {
+            format!(
+                "unable to read metadata as JSON from {}",
+                path.to_str().expect(PATH_TO_STRING_MSG)
+            )
+        })?;
+
+        // Renumber the metadata to match the revealed token
+        if let Some(fields) = json.as_object_mut() {
+            if fields.contains_key(&id_field) {
+                fields.insert(id_field.clone(), token.into());
+            }
+            // Values are templated with the revealed token number, and otherwise with the
+            // variables of the generated token
+            let planned = &plan[&generated];
+            let mut token_variables = trait_variables(fields, &attributes_field);
+            token_variables.insert(ID.to_string(), token.to_string());
+            token_variables.insert(DNA.to_string(), planned.dna.clone());
+            if let Some(edition) = &planned.edition {
+                token_variables.insert(EDITION.to_string(), edition.clone());
+            }
+            // Names use the token name of the edition of the generated token, as when generated
+            let name = planned
+                .edition
+                .as_ref()
+                .and_then(|edition| config.editions.iter().find(|e| &e.name == edition))
+                .and_then(|edition| edition.token_name.as_ref())
+                .unwrap_or(&config.name);
+            for (field, format) in [
+                (&name_field, Some(name)),
+                (&external_url_field, config.external_url.as_ref()),
+            ] {
+                let Some(format) = format.filter(|_| fields.contains_key(field)) else {
+                    continue;
+                };
+                let value = strfmt::strfmt(format, &token_variables).with_context(|| {
+                    format!("unable to template the '{field}' field of revealed token {token}")
+                })?;
+                fields.insert(field.clone(), value.into());
+            }
+        }
+
+        let revealed_file = revealed_path
+            .join(metadata::file_name(config.standard, token))
+            .into_os_string()
+            .into_string()
+            .expect(PATH_TO_STRING_MSG);
+        debug!("saving token {token} revealed metadata (generated as token {generated}) as '{revealed_file}'");
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&revealed_file)?;
+        serde_json::to_writer(file, &json)
+            .with_context(|| format!("error saving {revealed_file}"))?;
+    }
+
+    // Record the reveal, including the starting index within any provenance record
+    provenance::record_starting_index(&source.join(output), offset)
+        .with_context(|| "unable to record the starting index within the provenance record")?;
+    let reveal_path = source
+        .join(output)
+        .join(REVEAL_FILE_NAME)
+        .into_os_string()
+        .into_string()
+        .expect(PATH_TO_STRING_MSG);
+    debug!("saving reveal record as '{reveal_path}'");
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&reveal_path)?;
+    serde_json::to_writer_pretty(
+        file,
+        &Reveal {
+            offset,
+            start_token: tokens[0],
+            supply,
+            tokens,
+        },
+    )
+    .with_context(|| format!("error saving {reveal_path}"))?;
+
+    if !missing.is_empty() {
+        warn!(
+            "{} token(s) have no generated metadata and were not revealed: {}",
+            missing.len(),
+            missing
+                .iter()
+                .map(|t| t.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// The value of each trait within the metadata of a token, by trait type, as available when
+/// templating token names.
+fn trait_variables(fields: &Map<String, Value>, attributes: &str) -> HashMap<String, String> {
+    fields
+        .get(attributes)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+        .filter_map(|attribute| {
+            let (name, value) = match (
+                attribute
+                    .get("trait_type")
+                    .or_else(|| attribute.get("name")),
+                attribute.get("value"),
+            ) {
+                (Some(Value::String(name)), Some(value)) => (name, value),
+                // Attributes without a trait type are a single property and value
+                _ if attribute.len() == 1 => attribute.iter().next()?,
+                _ => return None,
+            };
+            let value = match value {
+                Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            Some((name.clone(), value))
+        })
+        .collect()
+}
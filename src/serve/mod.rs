@@ -0,0 +1,470 @@
+use crate::config::Standard;
+use crate::generation::plan;
+use crate::{metadata, output, Config, PATH_TO_STRING_MSG};
+use anyhow::{anyhow, Result};
+use indexmap::IndexMap;
+use log::{debug, info, warn};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::num::NonZeroUsize;
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, ResponseBox, Server, StatusCode};
+
+// The path of the built-in gallery, along with the token listing it is populated from
+const GALLERY_PATH: &str = "/_gallery/";
+const GALLERY_TOKENS_PATH: &str = "/_gallery/tokens.json";
+const GALLERY: &str = include_str!("gallery.html");
+// The number of requests handled concurrently when the available parallelism is unknown
+const DEFAULT_WORKERS: usize = 4;
+// The characters percent-encoded within a path segment of a url
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// A token listed within the gallery.
+#[derive(Serialize)]
+struct Token {
+    token: usize,
+    // The chosen value of each attribute, by attribute name.
+    attributes: IndexMap<String, String>,
+    // The url of the token image.
+    image: Option<String>,
+    // The url of the token metadata.
+    metadata: Option<String>,
+}
+
+/// Serves the output directory over HTTP, along with a gallery of the generated tokens.
+pub(crate) fn serve(
+    source: &Path,
+    output: &str,
+    media: &str,
+    metadata: &str,
+    config: &Config,
+    address: &str,
+    port: u16,
+) -> Result<()> {
+    let root = source.join(output);
+    let root_path = root.to_str().expect(PATH_TO_STRING_MSG);
+    if !root.is_dir() {
+        return Err(anyhow!(
+            "could not find output directory {root_path} - generate the collection and try again"
+        ));
+    }
+    output::check_editions(source, output)?;
+
+    let server = Server::http((address, port))
+        .map_err(|e| anyhow!("unable to serve on {address}:{port}: {e}"))?;
+    info!("serving '{root_path}' at http://{address}:{port}/");
+    info!("browse the gallery at http://{address}:{port}{GALLERY_PATH}");
+
+    // Handle requests concurrently, so that large media does not hold up other requests
+    let workers = thread::available_parallelism().map_or(DEFAULT_WORKERS, NonZeroUsize::get);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                for request in server.incoming_requests() {
+                    let response = respond(&root, media, metadata, config.standard, &request);
+                    debug!(
+                        "{} {} {}",
+                        request.method(),
+                        request.url(),
+                        response.status_code().0
+                    );
+                    if let Err(e) = request.respond(response) {
+                        warn!("unable to respond: {e}");
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn respond(
+    root: &Path,
+    media: &str,
+    metadata: &str,
+    standard: Standard,
+    request: &Request,
+) -> ResponseBox {
+    let response = match request.method() {
+        // Allow any cross-origin request
+        Method::Options => Response::empty(204)
+            .with_header(header("Access-Control-Allow-Methods", "GET, HEAD, OPTIONS"))
+            .with_header(header("Access-Control-Allow-Headers", "*"))
+            .boxed(),
+        Method::Get | Method::Head => {
+            let path = request.url().split(['?', '#']).next().unwrap_or_default();
+            match path {
+                GALLERY_PATH => html(GALLERY),
+                GALLERY_TOKENS_PATH => tokens(root, media, metadata, standard),
+                _ => match resolve(root, path) {
+                    Some(file) if file.is_dir() && !path.ends_with('/') => Response::empty(301)
+                        .with_header(header("Location", &format!("{path}/")))
+                        .boxed(),
+                    Some(directory) if directory.is_dir() => html(&listing(&directory, path)),
+                    Some(file) if file.is_file() => self::file(&file, request),
+                    _ => status(404, "Not Found"),
+                },
+            }
+        }
+        _ => status(405, "Method Not Allowed"),
+    };
+    // Output changes as the collection is generated again, so is always revalidated
+    response
+        .with_header(header("Access-Control-Allow-Origin", "*"))
+        .with_header(header("Cache-Control", "no-cache"))
+}
+
+/// Resolves the (percent-encoded) path of a request within the root directory, rejecting any path
+/// outside of the root directory.
+fn resolve(root: &Path, path: &str) -> Option<PathBuf> {
+    let path = percent_decode_str(path.strip_prefix('/')?)
+        .decode_utf8()
+        .ok()?;
+    let mut resolved = root.to_path_buf();
+    for component in Path::new(path.as_ref()).components() {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(resolved)
+}
+
+/// Responds with the file, or with the byte range of the file requested.
+fn file(path: &Path, request: &Request) -> ResponseBox {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!(
+                "unable to read {}: {e}",
+                path.to_str().expect(PATH_TO_STRING_MSG)
+            );
+            return status(500, "Internal Server Error");
+        }
+    };
+    let content_type = match path.extension() {
+        Some(_) => metadata::mime_type(path),
+        // Metadata is typically written without an extension, so identify JSON by its content
+        None => {
+            let mut start = [0u8; 64];
+            let read = file.read(&mut start).unwrap_or_default();
+            let json = start[..read]
+                .iter()
+                .find(|b| !b.is_ascii_whitespace())
+                .is_some_and(|b| *b == b'{' || *b == b'[');
+            if file.rewind().is_err() {
+                return status(500, "Internal Server Error");
+            }
+            match json {
+                true => "application/json".to_string(),
+                false => "application/octet-stream".to_string(),
+            }
+        }
+    };
+    let length = match file.metadata() {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return status(500, "Internal Server Error"),
+    };
+    let range_header = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Range"))
+        .map(|h| h.value.as_str());
+    let response = match range(range_header, length) {
+        ByteRange::Whole => Response::from_file(file).boxed(),
+        ByteRange::Partial(start, end) => {
+            if file.seek(SeekFrom::Start(start)).is_err() {
+                return status(500, "Internal Server Error");
+            }
+            let size = end - start + 1;
+            Response::new(
+                StatusCode(206),
+                vec![header(
+                    "Content-Range",
+                    &format!("bytes {start}-{end}/{length}"),
+                )],
+                file.take(size),
+                Some(size as usize),
+                None,
+            )
+            .boxed()
+        }
+        ByteRange::Unsatisfiable => status(416, "Range Not Satisfiable")
+            .with_header(header("Content-Range", &format!("bytes */{length}"))),
+    };
+    response
+        .with_header(header("Content-Type", &content_type))
+        .with_header(header("Accept-Ranges", "bytes"))
+}
+
+/// The byte range of a file requested by a request.
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Whole,
+    /// The first and last byte (inclusive).
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// The byte range requested by any `Range` header (e.g. `bytes=0-99`, `bytes=100-` or
+/// `bytes=-100`), within the length of the file. Multiple ranges and invalid headers are ignored,
+/// serving the whole file.
+fn range(header: Option<&str>, length: u64) -> ByteRange {
+    let Some(range) = header
+        .and_then(|header| header.trim().strip_prefix("bytes="))
+        .filter(|range| !range.contains(','))
+    else {
+        return ByteRange::Whole;
+    };
+    let Some((start, end)) = range.split_once('-') else {
+        return ByteRange::Whole;
+    };
+    let (start, end) = match (start.trim().parse::<u64>(), end.trim().parse::<u64>()) {
+        // The last bytes of the file
+        (Err(_), Ok(suffix)) if start.trim().is_empty() => match suffix.min(length) {
+            0 => return ByteRange::Unsatisfiable,
+            suffix => (length - suffix, length - 1),
+        },
+        (Ok(start), Err(_)) if end.trim().is_empty() => (start, length.saturating_sub(1)),
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(length.saturating_sub(1))),
+        _ => return ByteRange::Whole,
+    };
+    match start < length {
+        true => ByteRange::Partial(start, end),
+        false => ByteRange::Unsatisfiable,
+    }
+}
+
+/// Lists the tokens of the generation plan for the gallery, with the urls of their media and
+/// metadata.
+fn tokens(root: &Path, media: &str, metadata: &str, standard: Standard) -> ResponseBox {
+    let planned = match plan::load(root) {
+        Ok(planned) => planned,
+        Err(e) => {
+            warn!("{e:#}");
+            Vec::new()
+        }
+    };
+    let url = |directory: &str, file: &Path| {
+        format!(
+            "/{}/{}",
+            utf8_percent_encode(directory, PATH_SEGMENT),
+            utf8_percent_encode(
+                file.file_name()
+                    .and_then(|n| n.to_str())
+                    .expect(PATH_TO_STRING_MSG),
+                PATH_SEGMENT
+            )
+        )
+    };
+    let tokens: Vec<Token> = planned
+        .into_iter()
+        .map(|planned| {
+            let token = planned.token;
+            let metadata_file = Some(
+                root.join(metadata)
+                    .join(metadata::file_name(standard, token)),
+            )
+            .filter(|path| path.is_file());
+            Token {
+                token,
+                attributes: planned.attributes,
+                image: output::token_image(&root.join(media), token).map(|i| url(media, &i)),
+                metadata: metadata_file.map(|m| url(metadata, &m)),
+            }
+        })
+        .collect();
+    match serde_json::to_string(&tokens) {
+        Ok(json) => Response::from_string(json)
+            .with_header(header("Content-Type", "application/json"))
+            .boxed(),
+        Err(_) => status(500, "Internal Server Error"),
+    }
+}
+
+/// Lists the entries of a directory, with directories first and numbered files in numeric order.
+fn listing(directory: &Path, path: &str) -> String {
+    let mut entries: Vec<(bool, String)> = std::fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_str()?.to_string();
+                    Some((entry.path().is_dir(), name))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    entries.sort_by(|(a_dir, a), (b_dir, b)| {
+        b_dir
+            .cmp(a_dir)
+            .then_with(|| match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                _ => a.cmp(b),
+            })
+    });
+
+    let title = escape(&percent_decode_str(path).decode_utf8_lossy());
+    let mut html = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{title}</title></head><body><h1>{title}</h1><p><a href=\"{GALLERY_PATH}\">Gallery</a></p><ul>"
+    );
+    if path != "/" {
+        html.push_str("<li><a href=\"../\">../</a></li>");
+    }
+    for (is_directory, name) in entries {
+        let suffix = if is_directory { "/" } else { "" };
+        html.push_str(&format!(
+            "<li><a href=\"{}{suffix}\">{}{suffix}</a></li>",
+            utf8_percent_encode(&name, PATH_SEGMENT),
+            escape(&name)
+        ));
+    }
+    html.push_str("</ul></body></html>");
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html(html: &str) -> ResponseBox {
+    Response::from_string(html)
+        .with_header(header("Content-Type", "text/html; charset=utf-8"))
+        .boxed()
+}
+
+fn status(code: u16, reason: &str) -> ResponseBox {
+    Response::from_string(reason).with_status_code(code).boxed()
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("expected a valid header")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LENGTH: u64 = 1000;
+
+    #[test]
+    fn range_whole() {
+        assert_eq!(range(None, LENGTH), ByteRange::Whole);
+        assert_eq!(range(Some("items=0-99"), LENGTH), ByteRange::Whole);
+        assert_eq!(range(Some("bytes=abc"), LENGTH), ByteRange::Whole);
+        assert_eq!(range(Some("bytes=-"), LENGTH), ByteRange::Whole);
+        // Invalid ranges, where the last byte precedes the first, are ignored
+        assert_eq!(range(Some("bytes=100-99"), LENGTH), ByteRange::Whole);
+    }
+
+    #[test]
+    fn range_partial() {
+        assert_eq!(range(Some("bytes=0-99"), LENGTH), ByteRange::Partial(0, 99));
+        assert_eq!(
+            range(Some(" bytes=100-100 "), LENGTH),
+            ByteRange::Partial(100, 100)
+        );
+        assert_eq!(
+            range(Some("bytes=900-2000"), LENGTH),
+            ByteRange::Partial(900, 999)
+        );
+    }
+
+    #[test]
+    fn range_open() {
+        assert_eq!(
+            range(Some("bytes=100-"), LENGTH),
+            ByteRange::Partial(100, 999)
+        );
+        assert_eq!(
+            range(Some("bytes=999-"), LENGTH),
+            ByteRange::Partial(999, 999)
+        );
+    }
+
+    #[test]
+    fn range_suffix() {
+        assert_eq!(
+            range(Some("bytes=-100"), LENGTH),
+            ByteRange::Partial(900, 999)
+        );
+        // A suffix longer than the file is the whole file
+        assert_eq!(
+            range(Some("bytes=-2000"), LENGTH),
+            ByteRange::Partial(0, 999)
+        );
+        assert_eq!(range(Some("bytes=-0"), LENGTH), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn range_past_end() {
+        assert_eq!(
+            range(Some("bytes=1000-1099"), LENGTH),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(range(Some("bytes=1000-"), LENGTH), ByteRange::Unsatisfiable);
+        assert_eq!(range(Some("bytes=0-"), 0), ByteRange::Unsatisfiable);
+        assert_eq!(range(Some("bytes=-100"), 0), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn range_multiple() {
+        assert_eq!(range(Some("bytes=0-99,200-299"), LENGTH), ByteRange::Whole);
+        assert_eq!(range(Some("bytes=0-99, -100"), LENGTH), ByteRange::Whole);
+    }
+
+    #[test]
+    fn resolve_within_root() {
+        let root = Path::new("/srv/output");
+        assert_eq!(
+            resolve(root, "/media/1.png"),
+            Some(root.join("media/1.png"))
+        );
+        assert_eq!(
+            resolve(root, "/./metadata/1"),
+            Some(root.join("metadata/1"))
+        );
+        assert_eq!(
+            resolve(root, "/media/Season%201.png"),
+            Some(root.join("media/Season 1.png"))
+        );
+        assert_eq!(resolve(root, "/"), Some(root.to_path_buf()));
+    }
+
+    #[test]
+    fn resolve_traversal() {
+        let root = Path::new("/srv/output");
+        assert_eq!(resolve(root, "/../config.json"), None);
+        assert_eq!(resolve(root, "/media/../../config.json"), None);
+        assert_eq!(resolve(root, "/%2e%2e/config.json"), None);
+        assert_eq!(resolve(root, "/media/%2E%2E/%2e%2e/config.json"), None);
+        assert_eq!(resolve(root, "/..%2fconfig.json"), None);
+        assert_eq!(resolve(root, "/%ff"), None);
+    }
+
+    #[test]
+    fn resolve_absolute() {
+        let root = Path::new("/srv/output");
+        assert_eq!(resolve(root, "//etc/passwd"), None);
+        assert_eq!(resolve(root, "/%2fetc/passwd"), None);
+        assert_eq!(resolve(root, "%2fetc/passwd"), None);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::PATH_TO_STRING_MSG;
+use anyhow::{anyhow, Context, Result};
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use log::{debug, trace};
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::fontdb::{Database, Family, Query, Style, Weight};
+use resvg::usvg::{Options, Tree};
+use rusttype::{Font, Scale};
+use std::path::Path;
+
+// The colours of the sheet background, tile background and captions
+const BACKGROUND: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const TILE_BACKGROUND: Rgba<u8> = Rgba([235, 235, 235, 255]);
+const CAPTION_COLOR: Rgba<u8> = Rgba([40, 40, 40, 255]);
+// The height of each caption line, relative to the tile size (with a minimum legible height)
+const CAPTION_SCALE: f32 = 0.06;
+const MIN_CAPTION_HEIGHT: f32 = 12.0;
+// The height of the title, relative to the height of captions
+const TITLE_SCALE: f32 = 2.0;
+// The suffix of captions truncated to fit within the width of a tile
+const ELLIPSIS: &str = "...";
+
+/// An image of a sheet, with any caption lines shown beneath it.
+pub(crate) struct Tile {
+    pub(crate) image: DynamicImage,
+    pub(crate) caption: Vec<String>,
+}
+
+/// The layout of a sheet: a grid of square tiles, beneath any title.
+pub(crate) struct Layout<'a> {
+    /// The title of the sheet.
+    pub(crate) title: Option<&'a str>,
+    /// The number of tiles in each row.
+    pub(crate) columns: u32,
+    /// The width and height of each tile, in pixels.
+    pub(crate) tile_size: u32,
+    /// The space around each tile, in pixels.
+    pub(crate) padding: u32,
+    /// The font of any captions, which are otherwise not shown.
+    pub(crate) font: Option<&'a Font<'static>>,
+}
+
+impl Layout<'_> {
+    /// Composes the tiles into a single sheet image, in rows from left to right.
+    pub(crate) fn render(&self, tiles: &[Tile]) -> RgbaImage {
+        let columns = self.columns.min(tiles.len() as u32).max(1);
+        let rows = (tiles.len() as u32).div_ceil(columns).max(1);
+        let scale = Scale::uniform((self.tile_size as f32 * CAPTION_SCALE).max(MIN_CAPTION_HEIGHT));
+        let line_height = scale.y.ceil() as u32;
+        let lines = match self.font {
+            Some(_) => tiles.iter().map(|t| t.caption.len()).max().unwrap_or(0) as u32,
+            None => 0,
+        };
+        let cell_width = self.tile_size + self.padding;
+        let cell_height = self.tile_size + lines * line_height + self.padding;
+        let title_scale = Scale::uniform(scale.y * TITLE_SCALE);
+        let title_height = match (self.title, self.font) {
+            (Some(_), Some(_)) => title_scale.y.ceil() as u32 + self.padding,
+            _ => 0,
+        };
+
+        let mut sheet = RgbaImage::from_pixel(
+            columns * cell_width + self.padding,
+            title_height + rows * cell_height + self.padding,
+            BACKGROUND,
+        );
+        if let (Some(title), Some(font)) = (self.title, self.font) {
+            draw_text_mut(
+                &mut sheet,
+                CAPTION_COLOR,
+                self.padding as i32,
+                self.padding as i32,
+                title_scale,
+                font,
+                title,
+            );
+        }
+        for (i, tile) in tiles.iter().enumerate() {
+            let (x, y) = (
+                self.padding + (i as u32 % columns) * cell_width,
+                title_height + self.padding + (i as u32 / columns) * cell_height,
+            );
+            imageops::replace(
+                &mut sheet,
+                &RgbaImage::from_pixel(self.tile_size, self.tile_size, TILE_BACKGROUND),
+                x as i64,
+                y as i64,
+            );
+
+            // Fit the image within the tile, centred
+            let image = tile.image.thumbnail(self.tile_size, self.tile_size);
+            imageops::overlay(
+                &mut sheet,
+                &image,
+                (x + (self.tile_size - image.width()) / 2) as i64,
+                (y + (self.tile_size - image.height()) / 2) as i64,
+            );
+
+            let Some(font) = self.font else {
+                continue;
+            };
+            for (line, caption) in tile.caption.iter().enumerate() {
+                draw_text_mut(
+                    &mut sheet,
+                    CAPTION_COLOR,
+                    x as i32,
+                    (y + self.tile_size + line as u32 * line_height) as i32,
+                    scale,
+                    font,
+                    &truncate(font, caption, scale, self.tile_size),
+                );
+            }
+        }
+        sheet
+    }
+}
+
+fn truncate(font: &Font, caption: &str, scale: Scale, width: u32) -> String {
+    let fits = |text: &str| text_size(scale, font, text).0 <= width as i32;
+    if fits(caption) {
+        return caption.to_string();
+    }
+    let mut truncated: String = caption.to_string();
+    while !truncated.is_empty() && !fits(&format!("{truncated}{ELLIPSIS}")) {
+        truncated.pop();
+    }
+    format!("{truncated}{ELLIPSIS}")
+}
+
+/// Loads the font of captions from the path specified, otherwise a sans-serif system font.
+pub(crate) fn font(path: Option<&Path>) -> Result<Font<'static>> {
+    if let Some(path) = path {
+        let font_path = path.to_str().expect(PATH_TO_STRING_MSG);
+        trace!("loading caption font from '{font_path}'...");
+        let data =
+            std::fs::read(path).with_context(|| format!("unable to read font from {font_path}"))?;
+        return Font::try_from_vec(data).ok_or_else(|| anyhow!("unable to load font {font_path}"));
+    }
+
+    trace!("loading caption font from system fonts...");
+    let mut database = Database::new();
+    database.load_system_fonts();
+    database
+        .query(&Query {
+            families: &[Family::SansSerif],
+            ..Query::default()
+        })
+        .or_else(|| {
+            // Fall back to any regular sans-serif face, then any face
+            let sans = |name: &str| name.contains("Sans") && !name.contains("Serif");
+            database
+                .faces()
+                .find(|face| {
+                    face.weight == Weight::NORMAL
+                        && face.style == Style::Normal
+                        && face.families.iter().any(|(name, _)| sans(name))
+                })
+                .or_else(|| database.faces().next())
+                .map(|face| face.id)
+        })
+        .and_then(|id| {
+            database.with_face_data(id, |data, index| {
+                Font::try_from_vec_and_index(data.to_vec(), index)
+            })
+        })
+        .flatten()
+        .ok_or_else(|| anyhow!("no system font found for captions - specify a font and try again"))
+}
+
+/// Opens a media image, rasterizing an SVG document to fit the size specified.
+pub(crate) fn open(path: &Path, size: u32) -> Result<DynamicImage> {
+    let image_path = path.to_str().expect(PATH_TO_STRING_MSG);
+    trace!("opening '{image_path}'...");
+    if path.extension().and_then(|e| e.to_str()) != Some("svg") {
+        return image::open(path).with_context(|| format!("unable to open {image_path}"));
+    }
+
+    let data = std::fs::read(path).with_context(|| format!("unable to read {image_path}"))?;
+    let tree = Tree::from_data(&data, &Options::default())
+        .with_context(|| format!("unable to parse {image_path}"))?;
+    let scale = size as f32 / tree.size().width().max(tree.size().height());
+    let (width, height) = (
+        (tree.size().width() * scale).round().max(1.0) as u32,
+        (tree.size().height() * scale).round().max(1.0) as u32,
+    );
+    let mut pixmap =
+        Pixmap::new(width, height).ok_or_else(|| anyhow!("unable to rasterize {image_path}"))?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // Convert from premultiplied alpha
+    Ok(DynamicImage::ImageRgba8(RgbaImage::from_fn(
+        width,
+        height,
+        |x, y| {
+            let pixel = pixmap
+                .pixel(x, y)
+                .expect("could not get rasterized pixel")
+                .demultiply();
+            Rgba([pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()])
+        },
+    )))
+}
+
+/// Saves a sheet image as a PNG.
+pub(crate) fn save(sheet: &RgbaImage, path: &Path) -> Result<()> {
+    let sheet_path = path.to_str().expect(PATH_TO_STRING_MSG);
+    debug!("saving sheet as '{sheet_path}'");
+    sheet
+        .save(path)
+        .with_context(|| format!("error saving {sheet_path}"))
+}
@@ -0,0 +1,226 @@
+use crate::config::{Attribute, Edition, EditionOutput, Selection};
+use crate::generation::plan;
+use crate::random::{self, AttributeValue, Stats, Token};
+use crate::sheet::{self, Layout, Tile};
+use crate::{generation, output, Config, PATH_TO_STRING_MSG};
+use anyhow::{anyhow, Context, Result};
+use image::DynamicImage;
+use indexmap::IndexMap;
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+
+// The prefix of the uniquely named directory of the rendered options, within the temporary
+// directory
+const RENDER_DIRECTORY: &str = "ng-traits";
+// The directory names of the rendered media and metadata
+const MEDIA_DIRECTORY: &str = "media";
+const METADATA_DIRECTORY: &str = "metadata";
+
+/// The layout of the trait sheets.
+pub(crate) struct Options<'a> {
+    pub(crate) columns: u32,
+    pub(crate) tile_size: u32,
+    /// The font of labels, defaulting to a sans-serif system font.
+    pub(crate) font: Option<&'a Path>,
+}
+
+/// Renders each option of each attribute above the selected base options of other attributes,
+/// writing a sheet per attribute to the traits directory labelled with the weight, expected and
+/// actual percentage of each option.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn export(
+    source: &Path,
+    output: &str,
+    media: &str,
+    metadata: &str,
+    traits: &str,
+    config: Config,
+    base: &[Selection],
+    options: Options<'_>,
+) -> Result<()> {
+    // Sheets of each attribute are replaced, leaving any other files of the directory untouched
+    let traits = output::init_sheets(&source.join(output), traits, media, metadata)?;
+    let font = sheet::font(options.font)?;
+
+    // Check base options exist
+    let base: Vec<(&Attribute, &str)> = base
+        .iter()
+        .map(|selection| {
+            let attribute = config
+                .attributes
+                .iter()
+                .find(|a| a.name == selection.attribute)
+                .ok_or_else(|| anyhow!("unknown base attribute '{}'", selection.attribute))?;
+            if !attribute.options.contains_key(&selection.value) {
+                return Err(anyhow!(
+                    "unknown option '{}' of base attribute '{}'",
+                    selection.value,
+                    attribute.name
+                ));
+            }
+            Ok((attribute, selection.value.as_str()))
+        })
+        .collect::<Result<_>>()?;
+
+    // The actual percentage of each option is that of the collection, combined across editions
+    let mut stats: IndexMap<&str, IndexMap<&str, Stats>> = IndexMap::new();
+    for (edition, tokens) in collection(source, output, &config)? {
+        for (attribute, edition_stats) in random::stats(&config, edition, &tokens) {
+            let attribute_stats = stats.entry(attribute).or_default();
+            for (value, option_stats) in edition_stats {
+                match attribute_stats.get_mut(value) {
+                    Some(existing) => existing.add(&option_stats),
+                    None => {
+                        attribute_stats.insert(value, option_stats);
+                    }
+                }
+            }
+        }
+    }
+
+    // Each option is rendered in its layer context, as a token of the base options plus the option
+    let mut tokens: Vec<Token> = Vec::new();
+    for attribute in &config.attributes {
+        for (value, option) in &attribute.options {
+            tokens.push(
+                config
+                    .attributes
+                    .iter()
+                    .filter_map(|a| match a == attribute {
+                        true => Some((a, AttributeValue::from(value.as_str()), option)),
+                        false => base.iter().find(|(b, _)| b == &a).map(|(_, value)| {
+                            (a, AttributeValue::from(*value), &a.options[*value])
+                        }),
+                    })
+                    .collect(),
+            );
+        }
+    }
+
+    // Render within a temporary directory, leaving any generated output untouched
+    let render = output::init_temp(RENDER_DIRECTORY, MEDIA_DIRECTORY, METADATA_DIRECTORY)?;
+    let render_path = render.to_str().expect(PATH_TO_STRING_MSG);
+    info!(
+        "rendering {} option(s) within '{render_path}'...",
+        tokens.len()
+    );
+    let mut images = generation::render(
+        source,
+        render_path,
+        MEDIA_DIRECTORY,
+        METADATA_DIRECTORY,
+        &config,
+        &tokens,
+        options.tile_size,
+    )
+    .await?
+    .into_iter();
+
+    for attribute in &config.attributes {
+        let mut tiles = Vec::with_capacity(attribute.options.len());
+        let attribute_stats = stats.get(attribute.name.as_str()).ok_or_else(|| {
+            anyhow!(
+                "no tokens were found to determine the percentages of the '{}' attribute",
+                attribute.name
+            )
+        })?;
+        for (value, option_stats) in attribute_stats {
+            let image = match images.next().flatten() {
+                Some(image) => sheet::open(&image, options.tile_size)?,
+                // Options without any layers (e.g. none) are shown as an empty tile
+                None => DynamicImage::new_rgba8(1, 1),
+            };
+            tiles.push(Tile {
+                image,
+                caption: vec![
+                    value.to_string(),
+                    format!(
+                        "weight {} ({:.2}%)",
+                        attribute.options[*value].weight(),
+                        option_stats.expected_weight_percentage()
+                    ),
+                    format!("actual {:.2}%", option_stats.actual_percentage()),
+                ],
+            });
+        }
+
+        let layout = Layout {
+            title: Some(&attribute.name),
+            columns: options.columns,
+            tile_size: options.tile_size,
+            padding: options.tile_size / 16,
+            font: Some(&font),
+        };
+        let sheet_path = traits.join(format!("{}.png", attribute.name.replace(['/', '\\'], "_")));
+        sheet::save(&layout.render(&tiles), &sheet_path)?;
+        info!(
+            "trait sheet of '{}' saved as '{}'",
+            attribute.name,
+            sheet_path.to_str().expect(PATH_TO_STRING_MSG)
+        );
+    }
+    Ok(())
+}
+
+/// The tokens of each edition (or of the whole collection, without editions), as planned when the
+/// collection has been generated and otherwise randomly generated for the configured supply.
+fn collection<'a>(
+    source: &Path,
+    output: &str,
+    config: &'a Config,
+) -> Result<Vec<(Option<&'a Edition>, Vec<Token<'a>>)>> {
+    // Separately output editions are each planned within their own directory
+    let output = source.join(output);
+    let plan_directories: Vec<PathBuf> = match config.edition_output {
+        EditionOutput::Separate if !config.editions.is_empty() => config
+            .editions
+            .iter()
+            .map(|edition| output.join(&edition.name))
+            .collect(),
+        _ => vec![output],
+    };
+    if plan_directories
+        .iter()
+        .all(|directory| plan::exists(directory))
+    {
+        let mut planned = Vec::new();
+        for directory in &plan_directories {
+            planned.extend(plan::load(directory)?);
+        }
+        match plan::tokens(config, &planned) {
+            Ok(tokens) if tokens.is_empty() => {
+                warn!("the generation plan is empty - using a randomly generated collection instead")
+            }
+            Ok(tokens) => {
+                debug!("using the generation plan to determine actual percentages");
+                let mut collection: Vec<(Option<&Edition>, Vec<Token>)> = Vec::new();
+                for (_, token, edition) in tokens {
+                    match collection
+                        .iter_mut()
+                        .find(|(e, _)| e.map(|e| &e.name) == edition.map(|e| &e.name))
+                    {
+                        Some((_, tokens)) => tokens.push(token),
+                        None => collection.push((edition, vec![token])),
+                    }
+                }
+                return Ok(collection);
+            }
+            Err(e) => warn!(
+                "the generation plan no longer matches the configuration ({e:#}) - using a randomly generated collection instead"
+            ),
+        }
+    }
+
+    let editions: Vec<Option<&Edition>> = match config.editions.is_empty() {
+        true => vec![None],
+        false => config.editions.iter().map(Some).collect(),
+    };
+    editions
+        .into_iter()
+        .map(|edition| {
+            let tokens = random::generate(config, edition)
+                .with_context(|| "failed to generate the collection")?;
+            Ok((edition, tokens))
+        })
+        .collect()
+}
@@ -0,0 +1,131 @@
+use crate::config::{self, Config, Override};
+use crate::PATH_TO_STRING_MSG;
+use anyhow::{Context, Result};
+use log::{error, info, trace};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+// The time without further changes before changes are considered complete, as editors and exports
+// often write a file in several steps
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+// The extension of configuration files (including any extended or included configuration)
+const CONFIG_EXTENSION: &str = "json";
+
+/// A change within the source directory.
+pub(crate) enum Change {
+    /// A configuration file changed, requiring the configuration to be reloaded.
+    Config,
+    /// Other files changed, as absolute paths.
+    Files(Vec<PathBuf>),
+}
+
+/// Watches the source directory for changes, ignoring changes within any output directories.
+pub(crate) struct Watcher {
+    // Retained to keep watching until dropped
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    ignored: Vec<PathBuf>,
+}
+
+impl Watcher {
+    pub(crate) fn new(source: &Path, ignored: &[&str]) -> Result<Self> {
+        let source_path = source.to_str().expect(PATH_TO_STRING_MSG);
+        let source = source
+            .canonicalize()
+            .with_context(|| format!("unable to resolve source directory {source_path}"))?;
+        let (sender, events) = channel();
+        let mut watcher =
+            notify::recommended_watcher(sender).with_context(|| "unable to create file watcher")?;
+        watcher
+            .watch(&source, RecursiveMode::Recursive)
+            .with_context(|| format!("unable to watch {source_path}"))?;
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            ignored: ignored.iter().map(|path| source.join(path)).collect(),
+        })
+    }
+
+    /// Waits until files within the source directory change, returning once changes settle.
+    pub(crate) fn wait(&self) -> Result<Change> {
+        info!("watching for changes...");
+        let mut changed: Vec<PathBuf> = Vec::new();
+        loop {
+            // Block until the first change, then collect changes until they settle
+            let event = if changed.is_empty() {
+                self.events.recv()?
+            } else {
+                match self.events.recv_timeout(SETTLE_TIME) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(e) => return Err(e.into()),
+                }
+            };
+            let event = event.with_context(|| "unable to watch for changes")?;
+            if !matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                continue;
+            }
+            for path in event.paths {
+                if self.ignored.iter().any(|ignored| path.starts_with(ignored))
+                    || changed.contains(&path)
+                {
+                    continue;
+                }
+                trace!("'{}' changed", path.to_str().expect(PATH_TO_STRING_MSG));
+                changed.push(path);
+            }
+        }
+
+        if changed
+            .iter()
+            .any(|path| path.extension().and_then(|e| e.to_str()) == Some(CONFIG_EXTENSION))
+        {
+            info!("configuration changed, reloading...");
+            return Ok(Change::Config);
+        }
+        info!("{} file(s) changed", changed.len());
+        Ok(Change::Files(changed))
+    }
+
+    /// Waits until the configuration changes, ignoring changes to any other files.
+    pub(crate) fn wait_config(&self) -> Result<()> {
+        while let Change::Files(_) = self.wait()? {}
+        Ok(())
+    }
+}
+
+/// Loads the configuration, retrying each time the configuration changes when watching.
+pub(crate) fn load(
+    source: &Path,
+    config: &str,
+    overrides: &[Override],
+    watcher: Option<&Watcher>,
+) -> Result<Config> {
+    loop {
+        match (config::load(source, config, overrides), watcher) {
+            (Ok(config), _) => return Ok(config),
+            (Err(e), Some(watcher)) => {
+                error!("{e:#}");
+                watcher.wait_config()?;
+            }
+            (Err(e), None) => return Err(e),
+        }
+    }
+}
+
+/// Resolves a file within the source directory as an absolute path, comparable with changed files
+/// (even once the file has been removed).
+pub(crate) fn resolve(source: &Path, file: &Path) -> PathBuf {
+    let path = source.join(file);
+    path.canonicalize()
+        .or_else(|e| match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => parent.canonicalize().map(|parent| parent.join(name)),
+            _ => Err(e),
+        })
+        .unwrap_or(path)
+}